@@ -1,6 +1,7 @@
 use std::{env};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // Bring in everything - this is just our way to split things...
 use tstrust::test_runner::*;
@@ -11,12 +12,22 @@ use tstrust::test_runner::*;
 fn main() {
     let cfg = Config::instance();
 
+    if cfg.heap_leak_check_unavailable() {
+        println!("WARN: Heap leaks are not checked, tstrust is built without the 'heap-leak-check' feature");
+    }
+
+    if let Some(filename) = &cfg.journal_report {
+        print_journal_report(filename);
+        return;
+    }
 
     // Putting stuff in an 'app' instance - this 'solves' global variable problems..
     // Still having a global variable (temporary) since I need to track stuff the function under test is doing..
     // The global is reset between call's..
     let mut app = App::new();
     app.scan_libraries(&cfg.inputs);
+    app.apply_sharding();
+    app.schedule(&DurationEstimates::from_history(&History::new(&cfg.history_dir)));
 
     if cfg.list_tests {
         app.list_tests();
@@ -28,17 +39,46 @@ fn main() {
 
 
 
+fn print_journal_report(filename : &str) {
+    match JournalReport::load(Path::new(filename)) {
+        Ok(report) => report.print(),
+        Err(err) => println!("ERR: Unable to read journal '{}': {}", filename, err),
+    }
+}
+
 struct App {
     runners : Vec<TestRunner>,
+    // Loaded with --rerun-failed, only if the last run had failures
+    rerun_filter : Option<LastRun>,
 }
 impl App {
 
     pub fn new() -> App {
         let instance = App {
             runners : Vec::new(),
+            rerun_filter : Self::load_rerun_filter(),
         };
         return instance;
     }
+
+    // Same as other test frameworks; if nothing failed last time (or there is no last run) everything is executed
+    fn load_rerun_filter() -> Option<LastRun> {
+        let cfg = Config::instance();
+        if !cfg.rerun_failed {
+            return None;
+        }
+        match LastRun::load(Path::new(&cfg.last_run_file)) {
+            Ok(last_run) if last_run.has_failures() => Some(last_run),
+            Ok(_) => {
+                println!("No failures in last run, executing all tests");
+                None
+            },
+            Err(err) => {
+                println!("No last run state in '{}' ({}), executing all tests", cfg.last_run_file, err);
+                None
+            }
+        }
+    }
     fn scan_libraries(&mut self, inputs: &Vec<String>) {
         for x in inputs {
             self.scan_path_or_library(&x);
@@ -68,9 +108,57 @@ impl App {
     }
     fn scan_library(&mut self, filename: &str) {
         // TEST TEST
-        let tr = TestRunner::new(filename);
+        let mut tr = TestRunner::new(filename);
+        if let Some(last_run) = &self.rerun_filter {
+            tr.select_failed(last_run);
+        }
         self.runners.push(tr);
     }
+    // Longest libraries and modules first, so the run doesn't end waiting on a single long module
+    fn schedule(&mut self, estimates : &DurationEstimates) {
+        for runner in &mut self.runners {
+            runner.schedule_modules(estimates);
+        }
+        let mut durations : Vec<(f64, TestRunner)> = self.runners.drain(..).map(|runner| (runner.expected_duration(estimates), runner)).collect();
+        estimates.longest_first(&mut durations, |(duration_sec, _)| *duration_sec);
+        self.runners = durations.into_iter().map(|(_, runner)| runner).collect();
+    }
+
+    // Deselect all modules outside the current shard
+    // Without --shard-timings the modules are dealt round-robin, see 'Shard'
+    fn apply_sharding(&mut self) {
+        let shard = match Shard::from_config() {
+            Ok(Some(shard)) => shard,
+            Ok(None) => return,
+            Err(err) => {
+                println!("ERR: Invalid sharding, {}", err);
+                std::process::exit(1);
+            }
+        };
+
+        let timings = match &Config::instance().shard_timings {
+            Some(filename) => match DurationEstimates::from_file(Path::new(filename)) {
+                Ok(timings) => timings,
+                Err(err) => {
+                    println!("ERR: Unable to read shard timings '{}': {}", filename, err);
+                    std::process::exit(1);
+                }
+            },
+            None => DurationEstimates::default(),
+        };
+
+        let modules : Vec<(String, String)> = self.runners.iter().flat_map(|r| r.selected_modules()).collect();
+        if !timings.is_empty() && !modules.iter().any(|(library, module)| timings.is_known(library, module)) {
+            println!("WARN: No shard timings match the selected modules, all modules are expected to take the same time");
+        }
+        let in_shard = shard.select(modules, &timings);
+
+        println!("Shard {}: {} modules", shard, in_shard.len());
+        for runner in &mut self.runners {
+            runner.select_modules(&|library, module| in_shard.contains(&(library.to_string(), module.to_string())));
+        }
+    }
+
     fn list_tests(&self) {
         for runner in &self.runners {
             runner.list_tests();
@@ -80,22 +168,158 @@ impl App {
     fn execute_tests(&mut self) {
         let t_start = Instant::now();
 
-        println!("--> Start Global");
-        for runner in &mut self.runners {
-            runner.execute_tests();
+        let cfg = Config::instance();
+        // Without any repeat options we execute once, with --until-fail and no --repeat we go on until something fails
+        let max_iterations = match cfg.repeat {
+            Some(n) => n,
+            None if cfg.until_fail => u32::MAX,
+            None => 1,
+        };
+
+        if let Some(filename) = &cfg.journal {
+            let mut journal = RUN_JOURNAL.lock().unwrap();
+            match journal.open_file(Path::new(filename)) {
+                Ok(()) => journal.run_started(),
+                Err(err) => println!("ERR: Unable to open journal '{}': {}", filename, err),
+            }
+        }
+
+        install_interrupt_handler();
+        if let Some(budget) = cfg.time_budget {
+            start_time_budget(Duration::from_secs(budget));
+        }
+
+        let mut iterations = 0;
+        while iterations < max_iterations && stop_before_start().is_none() {
+            if iterations > 0 {
+                for runner in &mut self.runners {
+                    runner.reset();
+                }
+            }
+            iterations += 1;
+
+            let failed_before = self.count_failed();
+
+            println!("--> Start Global");
+            for runner in &mut self.runners {
+                runner.execute_tests();
+            }
+            println!("<-- End Global");
+
+            if cfg.until_fail && self.count_failed() > failed_before {
+                println!("Iteration {} failed, stopping", iterations);
+                break;
+            }
         }
-        println!("<-- End Global");
 
         let duration = t_start.elapsed();
 
         println!("");
         println!("-------------------");
         println!("Duration......: {} sec", duration.as_secs_f32());
+        if max_iterations > 1 {
+            println!("Iterations....: {}", iterations);
+        }
         for runner in &mut self.runners {
             runner.print_results();
         }
+        let stop_reason = run_stopped();
+        if let Some(reason) = stop_reason {
+            let not_executed : Vec<String> = self.runners.iter().flat_map(|r| r.not_executed_cases()).collect();
+            println!("Run stopped...: {}, {} tests not executed", reason, not_executed.len());
+            if !not_executed.is_empty() {
+                println!("Not executed:");
+                for symbol in &not_executed {
+                    println!("  {}", symbol);
+                }
+            }
+        }
+
+        let mut journal = RUN_JOURNAL.lock().unwrap();
+        if let Some(reason) = stop_reason {
+            journal.run_stopped(reason);
+        }
+        journal.run_finished(self.count_executed(), self.count_failed());
+        drop(journal);
+
+        self.save_last_run();
+        self.update_history();
+        self.save_bench_baseline();
+
+        if let Some(status) = stop_reason.and_then(|reason| reason.exit_status()) {
+            std::process::exit(status);
+        }
+    }
+
+    fn save_bench_baseline(&self) {
+        let cfg = Config::instance();
+        let Some(filename) = &cfg.bench_save_baseline else {
+            return;
+        };
+
+        let mut baseline = BenchBaseline::new();
+        for runner in &self.runners {
+            runner.add_bench_results(&mut baseline);
+        }
+        if let Err(err) = baseline.save(Path::new(filename)) {
+            println!("ERR: Unable to save bench baseline to '{}': {}", filename, err);
+        }
+    }
+
+    // Store this run in the history, the previous run is loaded first as the regression report compares against it
+    fn update_history(&self) {
+        let cfg = Config::instance();
+        if !cfg.history && !cfg.regression_report {
+            return;
+        }
+
+        let mut current = RunRecord::new();
+        for record in self.runners.iter().flat_map(|r| r.history_records()) {
+            current.add(record);
+        }
+
+        let history = History::new(&cfg.history_dir);
+        if cfg.regression_report {
+            match history.latest() {
+                Ok(Some(previous)) => {
+                    let exports : HashMap<String, HashSet<String>> = self.runners.iter().map(|r| r.exported_symbols()).collect();
+                    RegressionReport::new(&previous, &current, &exports, cfg.regression_threshold).print();
+                },
+                Ok(None) => println!("No previous run in '{}', regression report skipped", cfg.history_dir),
+                Err(err) => println!("ERR: Unable to read history from '{}': {}", cfg.history_dir, err),
+            }
+        }
 
+        if !cfg.history {
+            return;
+        }
+        if let Err(err) = history.save(&current).and_then(|_| history.prune(cfg.history_size)) {
+            println!("ERR: Unable to update history in '{}': {}", cfg.history_dir, err);
+        }
+    }
+
+    fn save_last_run(&self) {
+        let cfg = Config::instance();
+        if !cfg.save_last_run && !cfg.rerun_failed {
+            return;
+        }
+
+        let mut last_run = LastRun::new();
+        for entry in self.runners.iter().flat_map(|r| r.last_run_entries()) {
+            last_run.add(entry);
+        }
+
+        if let Err(err) = last_run.save(Path::new(&cfg.last_run_file)) {
+            println!("ERR: Unable to save last run state to '{}': {}", cfg.last_run_file, err);
+        }
+    }
+
+    fn count_executed(&self) -> i32 {
+        self.runners.iter().map(|r| r.count_results().0).sum()
+    }
 
+    fn count_failed(&self) -> i32 {
+        self.runners.iter().map(|r| r.count_results().1).sum()
     }
 
 }
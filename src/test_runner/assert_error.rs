@@ -1,15 +1,20 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TestFuncErrorClass {
     Error,
     Abort,
     Fatal,
+    Crash,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestFuncError {
     pub eclass: TestFuncErrorClass,
     pub file : String,
     pub line : u32,
     pub message : String,
+    #[serde(default)]
+    pub backtrace : Vec<String>,    // Symbolized frames, captured for fatal/abort and crashes
 }
 impl TestFuncError {
     pub fn new(eclass : TestFuncErrorClass, file : &str, line : u32, message : &str) -> TestFuncError {
@@ -18,12 +23,35 @@ impl TestFuncError {
             file : file.to_string(),
             line,
             message : message.to_string(),
+            backtrace : Vec::new(),
         }
     }
+    pub fn with_backtrace(mut self, backtrace : Vec<String>) -> TestFuncError {
+        self.backtrace = backtrace;
+        self
+    }
     pub fn print(&self) {
         // Ensure equal spacing with the logger from original test-runner
         print!("                                                                                     ");
         // Now print the error code..
-        println!("Assert Error: {}:{}\t'{}'", self.file, self.line, self.message);
+        match self.eclass {
+            TestFuncErrorClass::Crash => println!("Crash: '{}'", self.message),
+            _ if self.file.is_empty() => println!("Error: '{}'", self.message),
+            _ => println!("Assert Error: {}:{}\t'{}'", self.file, self.line, self.message),
+        }
+    }
+
+    pub fn print_backtrace(&self) {
+        for frame in &self.backtrace {
+            println!("    {}", frame);
+        }
+    }
+
+    // 'file:line, message' - or just the message when raised by the runner it-self (crashes, child process errors)
+    pub fn describe(&self) -> String {
+        match self.file.is_empty() {
+            true => self.message.clone(),
+            false => format!("{}:{}, {}", self.file, self.line, self.message),
+        }
     }
 }
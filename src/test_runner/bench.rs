@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use crate::test_runner::{canonical_library_name, Config, Singleton};
+
+// Welch's t above this is considered a significant difference (roughly 95% for larger sample sizes)
+const SIGNIFICANT_T : f64 = 2.0;
+
+//
+// Statistics over the per-iteration timings of a benchmarked test function (see --bench)
+// All values are in seconds.
+//
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchStats {
+    pub iterations : usize,
+    pub min : f64,
+    pub max : f64,
+    pub median : f64,
+    pub mean : f64,
+    pub stddev : f64,
+    pub p90 : f64,
+    pub p99 : f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchBaselineEntry {
+    pub library : String,
+    pub symbol : String,
+    pub stats : BenchStats,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BenchBaseline {
+    pub entries : Vec<BenchBaselineEntry>,
+}
+
+// Loaded once from '--bench-baseline', empty if none was given
+pub static BENCH_BASELINE: Lazy<BenchBaseline> = Lazy::new(|| {
+    match &Config::instance().bench_baseline {
+        None => BenchBaseline::default(),
+        Some(filename) => BenchBaseline::load(Path::new(filename)).unwrap_or_else(|err| {
+            println!("ERR: Unable to read bench baseline '{}': {}", filename, err);
+            BenchBaseline::default()
+        }),
+    }
+});
+
+impl BenchStats {
+    pub fn from_samples(samples : &[Duration]) -> Option<BenchStats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut secs : Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        secs.sort_by(f64::total_cmp);
+
+        let n = secs.len();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let stddev = if n > 1 {
+            (secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let median = if n.is_multiple_of(2) {
+            (secs[n / 2 - 1] + secs[n / 2]) / 2.0
+        } else {
+            secs[n / 2]
+        };
+
+        Some(BenchStats {
+            iterations : n,
+            min : secs[0],
+            max : secs[n - 1],
+            median,
+            mean,
+            stddev,
+            p90 : Self::percentile(&secs, 90.0),
+            p99 : Self::percentile(&secs, 99.0),
+        })
+    }
+
+    // Nearest-rank percentile, 'sorted' must be sorted ascending and not empty
+    fn percentile(sorted : &[f64], percent : f64) -> f64 {
+        let rank = ((percent / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    // Checks if this is slower than the baseline, returns a description if so
+    // Slower means the median increased more than 'threshold_percent' and the difference in mean is significant (Welch's t-test)
+    pub fn regression_from(&self, baseline : &BenchStats, threshold_percent : f64) -> Option<String> {
+        let limit = baseline.median * (1.0 + threshold_percent / 100.0);
+        if self.median <= limit {
+            return None;
+        }
+
+        let std_err = (self.stddev.powi(2) / self.iterations as f64 + baseline.stddev.powi(2) / baseline.iterations as f64).sqrt();
+        let t = if std_err > 0.0 { (self.mean - baseline.mean) / std_err } else { f64::INFINITY };
+        if t < SIGNIFICANT_T {
+            return None;
+        }
+
+        Some(format!("median {} sec, baseline {} sec (+{:.1}%, limit {}%)",
+                     self.median, baseline.median, (self.median / baseline.median - 1.0) * 100.0, threshold_percent))
+    }
+
+    pub fn print(&self) {
+        println!("  Bench: {} iterations, min {} sec, median {} sec, mean {} sec, stddev {} sec, p90 {} sec, p99 {} sec, max {} sec",
+                 self.iterations, self.min, self.median, self.mean, self.stddev, self.p90, self.p99, self.max);
+    }
+}
+
+impl BenchBaseline {
+    pub fn new() -> BenchBaseline {
+        Self::default()
+    }
+
+    pub fn add(&mut self, library : &str, symbol : &str, stats : &BenchStats) {
+        self.entries.push(BenchBaselineEntry {
+            library : canonical_library_name(library),
+            symbol : symbol.to_string(),
+            stats : stats.clone(),
+        });
+    }
+
+    pub fn find(&self, library : &str, symbol : &str) -> Option<&BenchStats> {
+        let library = canonical_library_name(library);
+        self.entries.iter().find(|e| e.library == library && e.symbol == symbol).map(|e| &e.stats)
+    }
+
+    pub fn load(filename : &Path) -> io::Result<BenchBaseline> {
+        let reader = BufReader::new(File::open(filename)?);
+        let baseline = serde_json::from_reader(reader)?;
+        Ok(baseline)
+    }
+
+    pub fn save(&self, filename : &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_millis(millis : &[u64]) -> Option<BenchStats> {
+        let samples : Vec<Duration> = millis.iter().map(|ms| Duration::from_millis(*ms)).collect();
+        BenchStats::from_samples(&samples)
+    }
+
+    #[test]
+    fn no_samples() {
+        assert!(from_millis(&[]).is_none());
+    }
+
+    #[test]
+    fn single_sample() {
+        let stats = from_millis(&[5]).unwrap();
+        assert_eq!(stats.iterations, 1);
+        assert_eq!(stats.min, 0.005);
+        assert_eq!(stats.max, 0.005);
+        assert_eq!(stats.median, 0.005);
+        assert_eq!(stats.p90, 0.005);
+        assert_eq!(stats.p99, 0.005);
+        assert_eq!(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn median_of_even_and_odd() {
+        // Samples are sorted first
+        assert_eq!(from_millis(&[3, 1, 2]).unwrap().median, 0.002);
+        assert_eq!(from_millis(&[4, 1, 3, 2]).unwrap().median, 0.0025);
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let millis : Vec<u64> = (1..=100).rev().collect();
+        let stats = from_millis(&millis).unwrap();
+        assert_eq!(stats.min, 0.001);
+        assert_eq!(stats.max, 0.1);
+        assert_eq!(stats.p90, 0.09);
+        assert_eq!(stats.p99, 0.099);
+
+        // With few samples the percentiles are the largest one
+        let stats = from_millis(&[1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(stats.p90, 0.005);
+        assert_eq!(stats.p99, 0.005);
+    }
+
+    #[test]
+    fn mean_and_sample_stddev() {
+        let stats = from_millis(&[2, 4, 4, 4, 5, 5, 7, 9]).unwrap();
+        assert!((stats.mean - 0.005).abs() < 1e-12);
+        assert!((stats.stddev - 0.002138089935299395).abs() < 1e-12);
+    }
+}
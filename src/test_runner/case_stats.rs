@@ -0,0 +1,84 @@
+use std::time::Duration;
+use crate::test_runner::TestResult;
+
+//
+// Aggregated statistics for a single case over several iterations (see --repeat/--until-fail)
+//
+#[derive(Debug, Clone)]
+pub struct CaseStats {
+    pub symbol : String,
+    pub runs : u32,
+    pub passed : u32,
+    pub failed : u32,
+    pub durations : Vec<Duration>,
+}
+
+impl CaseStats {
+    pub fn new(symbol : &str) -> CaseStats {
+        Self {
+            symbol : symbol.to_string(),
+            runs : 0,
+            passed : 0,
+            failed : 0,
+            durations : Vec::new(),
+        }
+    }
+
+    // Group results per symbol, keeps the order in which the cases were first executed
+    pub fn from_results<'a>(results : impl Iterator<Item = &'a TestResult>) -> Vec<CaseStats> {
+        let mut stats : Vec<CaseStats> = Vec::new();
+        for result in results {
+            let idx = match stats.iter().position(|s| s.symbol == result.symbol) {
+                Some(idx) => idx,
+                None => {
+                    stats.push(CaseStats::new(&result.symbol));
+                    stats.len() - 1
+                }
+            };
+            stats[idx].add_result(result);
+        }
+        stats
+    }
+
+    pub fn add_result(&mut self, result : &TestResult) {
+        self.runs += 1;
+        if result.did_fail() {
+            self.failed += 1;
+        } else {
+            self.passed += 1;
+        }
+        self.durations.push(result.exec_duration);
+    }
+
+    pub fn min_sec(&self) -> f32 {
+        self.durations.iter().min().map(Duration::as_secs_f32).unwrap_or_default()
+    }
+
+    pub fn max_sec(&self) -> f32 {
+        self.durations.iter().max().map(Duration::as_secs_f32).unwrap_or_default()
+    }
+
+    pub fn mean_sec(&self) -> f32 {
+        if self.durations.is_empty() {
+            return 0f32;
+        }
+        self.durations.iter().map(Duration::as_secs_f32).sum::<f32>() / self.durations.len() as f32
+    }
+
+    pub fn stddev_sec(&self) -> f32 {
+        if self.durations.len() < 2 {
+            return 0f32;
+        }
+        let mean = self.mean_sec();
+        let variance = self.durations.iter()
+            .map(|d| (d.as_secs_f32() - mean).powi(2))
+            .sum::<f32>() / (self.durations.len() - 1) as f32;
+        variance.sqrt()
+    }
+
+    pub fn print(&self) {
+        println!("  {}: runs {}, pass {}, fail {}, min {} sec, mean {} sec, max {} sec, stddev {} sec",
+                 self.symbol, self.runs, self.passed, self.failed,
+                 self.min_sec(), self.mean_sec(), self.max_sec(), self.stddev_sec());
+    }
+}
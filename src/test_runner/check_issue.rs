@@ -0,0 +1,30 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+//
+// Something a checker (fd leaks, thread leaks, ...) found while executing a test
+// Issues are warnings unless the checker runs in strict mode, then they fail the test.
+//
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckIssue {
+    pub checker : String,
+    pub message : String,
+    pub is_failure : bool,
+}
+
+impl CheckIssue {
+    pub fn new(checker : &str, message : &str, is_failure : bool) -> CheckIssue {
+        Self {
+            checker : checker.to_string(),
+            message : message.to_string(),
+            is_failure,
+        }
+    }
+}
+
+impl fmt::Display for CheckIssue {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = if self.is_failure { "Error" } else { "Warning" };
+        write!(f, "{} [{}]: {}", level, self.checker, self.message)
+    }
+}
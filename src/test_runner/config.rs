@@ -23,6 +23,14 @@ pub struct Config {
     #[arg(short='t', value_parser, value_delimiter= ',', default_values=["-"].to_vec())]
     pub testcases : Vec<String>,
 
+    /// Define a parameter for the tests, key=value, can be specified multiple times
+    #[arg(short='D', value_name = "KEY=VALUE")]
+    pub defines : Vec<String>,
+
+    /// Configuration file, parameters are read from the '[params]' section
+    #[arg(long)]
+    pub config_file : Option<String>,
+
     /// Specify global main function name
     #[arg(long, default_value_t = ("main").to_string())]
     pub main_func_name : String,
@@ -77,10 +85,182 @@ pub struct Config {
     pub stop_on_all_fail : bool,
 
 
+    /// Repeat the selected tests N times, with --until-fail this is the maximum number of iterations
+    #[arg(long)]
+    pub repeat : Option<u32>,
+
+    /// Repeat the selected tests until an iteration fails
+    #[arg(long, default_value_t = false)]
+    pub until_fail : bool,
+
+    /// Re-execute failing tests up to N times, tests passing on a retry are reported as flaky
+    #[arg(long, default_value_t = 0)]
+    pub retries : u32,
+
+    /// Stop the run after the first failing case, exit functions are still executed
+    #[arg(long, default_value_t = false, conflicts_with = "max_failures")]
+    pub fail_fast : bool,
+
+    /// Stop the run after N (at least 1) failing cases, exit functions are still executed
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_failures : Option<u32>,
+
+    /// Stop starting new cases once the run has taken SEC seconds, exit functions are still executed
+    #[arg(long, value_name = "SEC")]
+    pub time_budget : Option<u64>,
+
+    /// Execute only shard N (0 based) of the selected modules, defaults to TEST_SHARD_INDEX
+    #[arg(long, value_name = "N")]
+    pub shard_index : Option<u32>,
+
+    /// Number of shards the selected modules are split over, defaults to TEST_TOTAL_SHARDS
+    #[arg(long, value_name = "N")]
+    pub shard_count : Option<u32>,
+
+    /// Balance the shards on the case durations in this run file (as stored in the history), must be the same for all shards
+    #[arg(long, value_name = "FILE")]
+    pub shard_timings : Option<String>,
+
+    /// Execute only the cases that failed in the previous run (all if none failed), implies --save-last-run
+    #[arg(long, default_value_t = false)]
+    pub rerun_failed : bool,
+
+    /// Save the state of this run to the last run file, needed for a later --rerun-failed
+    #[arg(long, default_value_t = false)]
+    pub save_last_run : bool,
+
+    /// File holding the state of the last run, used by --rerun-failed
+    #[arg(long, default_value_t = (".tstrust-lastrun").to_string())]
+    pub last_run_file : String,
+
+    /// Directory where the results of each run are stored
+    #[arg(long, default_value_t = (".tstrust-history").to_string())]
+    pub history_dir : String,
+
+    /// Store the results of this run in the history
+    #[arg(long, default_value_t = false)]
+    pub history : bool,
+
+    /// Number of runs to keep in the history
+    #[arg(long, default_value_t = 20)]
+    pub history_size : usize,
+
+    /// Compare this run with the previous one in the history
+    #[arg(long, default_value_t = false)]
+    pub regression_report : bool,
+
+    /// Duration increase (in percent) reported as a regression
+    #[arg(long, default_value_t = 20.0)]
+    pub regression_threshold : f64,
+
+    /// Benchmark mode, regular test cases are executed repeatedly and timed per iteration
+    #[arg(long, default_value_t = false)]
+    pub bench : bool,
+
+    /// Number of timed iterations in benchmark mode
+    #[arg(long, default_value_t = 100)]
+    pub bench_iterations : u32,
+
+    /// Number of warm-up iterations (not timed) in benchmark mode
+    #[arg(long, default_value_t = 10)]
+    pub bench_warmup : u32,
+
+    /// Save benchmark results as baseline to this file
+    #[arg(long)]
+    pub bench_save_baseline : Option<String>,
+
+    /// Compare benchmark results against a baseline file, slower cases fail
+    #[arg(long)]
+    pub bench_baseline : Option<String>,
+
+    /// Median increase (in percent) allowed compared to the baseline
+    #[arg(long, default_value_t = 10.0)]
+    pub bench_threshold : f64,
+
+    /// Execute each test case in a child process
+    #[arg(long, default_value_t = false)]
+    pub isolate : bool,
+
+    /// Address space limit per test case in MB, implies --isolate
+    #[arg(long, value_name = "MB")]
+    pub limit_as : Option<u64>,
+
+    /// CPU time limit per test case in seconds, implies --isolate
+    #[arg(long, value_name = "SEC")]
+    pub limit_cpu : Option<u64>,
+
+    /// Open file descriptor limit per test case, implies --isolate
+    #[arg(long, value_name = "N")]
+    pub limit_nofile : Option<u64>,
+
+    /// Core file size limit per test case in MB, 0 disables core dumps, implies --isolate
+    #[arg(long, value_name = "MB")]
+    pub limit_core : Option<u64>,
+
+    /// Catch crash signals (SIGSEGV, SIGBUS, SIGFPE, SIGABRT) on the test thread and fail the case instead of the run
+    #[arg(long, default_value_t = false)]
+    pub catch_signals : bool,
+
+    /// Report file descriptors left open by a test case
+    #[arg(long, default_value_t = false)]
+    pub check_fd_leaks : bool,
+
+    /// Fail test cases leaving file descriptors open, implies --check-fd-leaks
+    #[arg(long, default_value_t = false)]
+    pub fail_on_fd_leaks : bool,
+
+    /// Report threads left running by a test case
+    #[arg(long, default_value_t = false)]
+    pub check_thread_leaks : bool,
+
+    /// Fail test cases leaving threads running, implies --check-thread-leaks
+    #[arg(long, default_value_t = false)]
+    pub fail_on_thread_leaks : bool,
+
+    /// Report heap memory allocated but not freed by a test case (needs the 'heap-leak-check' feature)
+    #[arg(long, default_value_t = false)]
+    pub check_heap_leaks : bool,
+
+    /// Fail test cases leaking heap memory, implies --check-heap-leaks (needs the 'heap-leak-check' feature)
+    #[arg(long, default_value_t = false)]
+    pub fail_on_heap_leaks : bool,
+
+    /// Capture allocation backtraces for leaked heap blocks, implies --check-heap-leaks (needs the 'heap-leak-check' feature)
+    #[arg(long, default_value_t = false)]
+    pub heap_leak_backtraces : bool,
+
+    /// Report changes to process wide state (environment, working directory, umask, signals, locale) made by a test case
+    #[arg(long, default_value_t = false)]
+    pub check_global_state : bool,
+
+    /// Fail test cases changing process wide state, implies --check-global-state
+    #[arg(long, default_value_t = false)]
+    pub fail_on_global_state : bool,
+
+    /// Append run progress (run and case start/end) to a journal file, flushed as the run goes
+    #[arg(long, value_name = "FILE")]
+    pub journal : Option<String>,
+
+    /// Print a (partial) report from a journal file, naming the case that was running if the run did not complete
+    #[arg(long, value_name = "FILE")]
+    pub journal_report : Option<String>,
+
     /// Suppress progress messages
     #[arg(short='s', default_value_t = false)]
     pub suppress_progress : bool,
 
+    /// Change working directory to the per-test temporary directory while executing
+    #[arg(long, default_value_t = false)]
+    pub chdir_temp : bool,
+
+    /// Keep per-test temporary directories, by default they are only kept for failed tests
+    #[arg(long, default_value_t = false)]
+    pub keep_temp : bool,
+
+    /// Test data directory, supports {libdir}, {libname} and {module} placeholders
+    #[arg(long, default_value_t = ("{libdir}/{libname}.data").to_string())]
+    pub data_dir : String,
+
     /// Discard test result code handling
     #[arg(short='r', default_value_t = false)]
     pub discard_test_return_code : bool,
@@ -91,6 +271,42 @@ pub struct Config {
 }
 
 
+impl Config {
+    pub fn is_fd_leak_check_enabled(&self) -> bool {
+        self.check_fd_leaks || self.fail_on_fd_leaks
+    }
+
+    pub fn is_thread_leak_check_enabled(&self) -> bool {
+        self.check_thread_leaks || self.fail_on_thread_leaks
+    }
+
+    // Without the 'heap-leak-check' feature the options are accepted but nothing is checked, see 'heap_leak_check_unavailable'
+    pub fn is_heap_leak_check_enabled(&self) -> bool {
+        cfg!(feature = "heap-leak-check") && self.is_heap_leak_check_requested()
+    }
+
+    // Heap leak checks asked for, but the runner was built without them
+    pub fn heap_leak_check_unavailable(&self) -> bool {
+        !cfg!(feature = "heap-leak-check") && self.is_heap_leak_check_requested()
+    }
+
+    fn is_heap_leak_check_requested(&self) -> bool {
+        self.check_heap_leaks || self.fail_on_heap_leaks || self.heap_leak_backtraces
+    }
+
+    pub fn is_global_state_check_enabled(&self) -> bool {
+        self.check_global_state || self.fail_on_global_state
+    }
+
+    // Number of failing cases stopping the run, --fail-fast is the same as '--max-failures 1'
+    pub fn failure_limit(&self) -> Option<u32> {
+        match self.fail_fast {
+            true => Some(1),
+            false => self.max_failures,
+        }
+    }
+}
+
 impl Singleton for Config {
     fn instance() -> &'static Self {
         static mut GLB_CONFIG_SINGLETON: MaybeUninit<Config> = MaybeUninit::uninit();
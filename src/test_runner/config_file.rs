@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::{fs, io};
+use once_cell::sync::Lazy;
+use crate::test_runner::{Config, Singleton};
+
+//
+// Optional configuration file, a plain ini-style file with '[section]' headers and 'key = value' lines
+// Lines starting with '#' or ';' are comments.
+//
+// [params]
+// server_port = 8080
+//
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    sections : HashMap<String, Vec<(String, String)>>,
+}
+
+// Loaded once from '--config-file', empty if none was given
+pub static CONFIG_FILE: Lazy<ConfigFile> = Lazy::new(|| {
+    match &Config::instance().config_file {
+        None => ConfigFile::default(),
+        Some(filename) => ConfigFile::load(filename).unwrap_or_else(|err| {
+            println!("ERR: Unable to read config file '{}': {}", filename, err);
+            ConfigFile::default()
+        }),
+    }
+});
+
+impl ConfigFile {
+    pub fn load(filename : &str) -> io::Result<ConfigFile> {
+        let data = fs::read_to_string(filename)?;
+        Ok(Self::parse(&data))
+    }
+
+    pub fn parse(data : &str) -> ConfigFile {
+        let mut config_file = ConfigFile::default();
+        let mut section = String::new();
+
+        for line in data.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len()-1].trim().to_string();
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) => {
+                    config_file.sections
+                        .entry(section.clone())
+                        .or_default()
+                        .push((key.trim().to_string(), value.trim().to_string()));
+                },
+                None => println!("WARN: Ignoring invalid config line '{}'", line),
+            }
+        }
+        config_file
+    }
+
+    // Returns the key/value pairs of a section in file order, empty if the section doesn't exist
+    pub fn section(&self, name : &str) -> &[(String, String)] {
+        match self.sections.get(name) {
+            Some(values) => values,
+            None => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(values : &[(&str, &str)]) -> Vec<(String, String)> {
+        values.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn sections_in_file_order() {
+        let config_file = ConfigFile::parse("[params]\nb = 2\na = 1\n\n[other]\nc = 3\n");
+        assert_eq!(config_file.section("params"), pairs(&[("b", "2"), ("a", "1")]).as_slice());
+        assert_eq!(config_file.section("other"), pairs(&[("c", "3")]).as_slice());
+        assert!(config_file.section("missing").is_empty());
+    }
+
+    #[test]
+    fn whitespace_and_comments() {
+        let config_file = ConfigFile::parse("# comment\n ; comment\n [ params ] \n  key  =  some value  \n");
+        assert_eq!(config_file.section("params"), pairs(&[("key", "some value")]).as_slice());
+    }
+
+    #[test]
+    fn values_may_contain_separator() {
+        let config_file = ConfigFile::parse("[params]\nurl = http://host/?a=b\nempty =\n");
+        assert_eq!(config_file.section("params"), pairs(&[("url", "http://host/?a=b"), ("empty", "")]).as_slice());
+    }
+
+    #[test]
+    fn keys_before_section_and_invalid_lines() {
+        let config_file = ConfigFile::parse("top = 1\n[params]\nno separator\nkey = 2\n");
+        assert_eq!(config_file.section(""), pairs(&[("top", "1")]).as_slice());
+        assert_eq!(config_file.section("params"), pairs(&[("key", "2")]).as_slice());
+    }
+
+    #[test]
+    fn repeated_sections_are_merged() {
+        let config_file = ConfigFile::parse("[params]\na = 1\n[other]\nb = 2\n[params]\na = 3\n");
+        assert_eq!(config_file.section("params"), pairs(&[("a", "1"), ("a", "3")]).as_slice());
+    }
+}
@@ -1,8 +1,21 @@
-use crate::test_runner::{TestFuncErrorClass, TestFuncError, PrePostCaseHandler};
+use std::ffi::CString;
+use std::time::Duration;
+use crate::test_runner::{CaseDeathExpectation, CheckIssue, DeathExpectation, TestFuncErrorClass, TestFuncError, PrePostCaseHandler, ResourceUsage, UsageSnapshot};
 
+#[derive(Default)]
 pub struct Context {
     pub raw_result : i32,
     pub dependencies : Vec<CaseDependency>,
+    pub case_params : Vec<CaseParams>,
+    pub death_expectations : Vec<CaseDeathExpectation>,
+    pub current_param : Option<CString>,
+    pub undefined_params : Vec<String>,
+    pub temp_dir : Option<CString>,
+    pub data_dir : Option<CString>,
+    pub bench_samples : Vec<Duration>,
+    pub usage_start : Option<UsageSnapshot>,
+    pub resource_usage : Option<ResourceUsage>,
+    pub check_issues : Vec<CheckIssue>,
     pub func_error : Option<TestFuncError>,
     pub pre_case_handler : Option<PrePostCaseHandler>,
     pub post_case_handler : Option<PrePostCaseHandler>,
@@ -12,37 +25,24 @@ pub struct CaseDependency {
     pub dependencies : Vec<String>,
 }
 
-impl Default for Context {
-    fn default() -> Self {
-        Self {
-            raw_result : 0,
-            dependencies : Vec::new(),
-            func_error : None,
-            pre_case_handler : None,
-            post_case_handler : None,
-        }
-    }
+// A named parameter set for a test case, declared as 'name=value' (or just 'name') through 'case_params'
+#[derive(Debug, Clone)]
+pub struct CaseParam {
+    pub name : String,
+    pub value : String,
 }
+pub struct CaseParams {
+    pub case : String,
+    pub params : Vec<CaseParam>,
+}
+
 impl Context {
     pub fn new() -> Context {
-        Self {
-            raw_result : 0,
-            dependencies : Vec::new(),
-            func_error : None,
-            pre_case_handler : None,
-            post_case_handler : None,
-        }
+        Self::default()
     }
 
-    // FIXME: check if possible to use 'default' instead..
-    pub fn reset(&mut self) -> Context {
-        Self {
-            raw_result : 0,
-            dependencies : Vec::new(),
-            func_error : None,
-            pre_case_handler : None,
-            post_case_handler : None,
-        }
+    pub fn reset(&mut self) {
+        *self = Self::default();
     }
     pub fn add_dependency(&mut self, case: &str, deplist: &str) {
         let parts: Vec<_> = deplist.split(",").collect();
@@ -58,14 +58,32 @@ impl Context {
         self.dependencies.push(case_dep);
 
     }
-    pub fn set_assert_error(&mut self, eclass: TestFuncErrorClass, line : u32, file : &str, message : &str) {
-        let func_error = TestFuncError {
-            eclass,
-            line,
-            file : file.to_string(),
-            message : message.to_string(),
+    pub fn add_case_params(&mut self, case: &str, paramlist: &str) {
+        let mut case_params = CaseParams {
+            case : case.to_string(),
+            params : Vec::new(),
         };
-        self.func_error = Some(func_error);
+
+        for p in paramlist.split(",").map(str::trim).filter(|p| !p.is_empty()) {
+            let param = match p.split_once('=') {
+                Some((name, value)) => CaseParam { name : name.trim().to_string(), value : value.trim().to_string() },
+                None => CaseParam { name : p.to_string(), value : p.to_string() },
+            };
+            case_params.params.push(param);
+        }
+        self.case_params.push(case_params);
+    }
+    pub fn add_death_expectation(&mut self, case : &str, expectation : DeathExpectation) {
+        self.death_expectations.push(CaseDeathExpectation {
+            case : case.to_string(),
+            expectation,
+        });
+    }
+    pub fn set_current_param(&mut self, param : Option<&CaseParam>) {
+        self.current_param = param.and_then(|p| CString::new(p.value.as_str()).ok());
+    }
+    pub fn set_assert_error(&mut self, eclass: TestFuncErrorClass, line : u32, file : &str, message : &str) {
+        self.func_error = Some(TestFuncError::new(eclass, file, line, message));
     }
 
     pub fn dump(&self) {
@@ -76,5 +94,12 @@ impl Context {
                 println!("    {}", case);
             }
         }
+        println!("Context, parameters");
+        for case_params in &self.case_params {
+            println!("  test case: {}", case_params.case);
+            for param in &case_params.params {
+                println!("    {}={}", param.name, param.value);
+            }
+        }
     }
 }
@@ -0,0 +1,172 @@
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::{mem, ptr};
+use libc::c_int;
+use crate::test_runner::{capture_frames, prepare_backtrace, pthread_exit, signal_name, test_frames, MAX_BACKTRACE_FRAMES};
+
+//
+// In-process crash recovery, see --catch-signals
+// A crash signal raised on the test thread terminates only that thread (the same way a failed assert does) and is
+// reported as a crash. The handler runs on an alternate stack, so a stack overflow can be caught as well.
+// Crashes on any other thread are left to the default action.
+//
+const CRASH_SIGNALS : [c_int; 4] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGFPE, libc::SIGABRT];
+const ALT_STACK_SIZE : usize = 64 * 1024;
+
+// Set by the signal handler, read once the test thread has been joined
+static CRASH_SIGNAL : AtomicI32 = AtomicI32::new(0);
+static CRASH_ADDRESS : AtomicUsize = AtomicUsize::new(0);
+static CRASH_HAS_ADDRESS : AtomicBool = AtomicBool::new(false);
+static CRASH_FRAMES : [AtomicUsize; MAX_BACKTRACE_FRAMES] = [const { AtomicUsize::new(0) }; MAX_BACKTRACE_FRAMES];
+static CRASH_NUM_FRAMES : AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static ON_TEST_THREAD : Cell<bool> = const { Cell::new(false) };
+}
+
+extern "C-unwind" fn crash_handler(signal : c_int, info : *mut libc::siginfo_t, _ucontext : *mut c_void) {
+    let on_test_thread = ON_TEST_THREAD.try_with(|on_test_thread| on_test_thread.replace(false)).unwrap_or(false);
+    if !on_test_thread {
+        // Returning raises the signal again (the fault is repeated, abort raises again) with the default action
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+        return;
+    }
+
+    // Only faults reported by the kernel have a meaningful address
+    let has_address = signal != libc::SIGABRT && unsafe { (*info).si_code } > 0;
+    if has_address {
+        CRASH_ADDRESS.store(unsafe { (*info).si_addr() } as usize, Ordering::SeqCst);
+    }
+    CRASH_HAS_ADDRESS.store(has_address, Ordering::SeqCst);
+
+    let mut frames = [0usize; MAX_BACKTRACE_FRAMES];
+    let num_frames = capture_frames(&mut frames);
+    for (slot, frame) in CRASH_FRAMES.iter().zip(&frames[..num_frames]) {
+        slot.store(*frame, Ordering::SeqCst);
+    }
+    CRASH_NUM_FRAMES.store(num_frames, Ordering::SeqCst);
+    CRASH_SIGNAL.store(signal, Ordering::SeqCst);
+
+    unsafe {
+        pthread_exit(ptr::null_mut());
+    }
+}
+
+//
+// A crash caught on the test thread
+//
+pub struct CrashInfo {
+    pub signal : c_int,
+    pub address : Option<usize>,
+    pub frames : Vec<usize>,    // Test code frames, starting at the faulting function
+}
+
+impl fmt::Display for CrashInfo {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address {
+            Some(address) => write!(f, "crashed with {} at address {:#x}", signal_name(self.signal), address),
+            None => write!(f, "crashed with {}", signal_name(self.signal)),
+        }
+    }
+}
+
+//
+// Crash signal handlers for the duration of a test case, the previous handlers are restored when dropped
+//
+pub struct CrashGuard {
+    previous : Vec<(c_int, libc::sigaction)>,
+}
+
+impl CrashGuard {
+    pub fn install() -> CrashGuard {
+        CRASH_SIGNAL.store(0, Ordering::SeqCst);
+        prepare_backtrace();
+
+        let mut action : libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = crash_handler as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+
+        let mut previous = Vec::new();
+        for signal in CRASH_SIGNALS {
+            let mut old_action : libc::sigaction = unsafe { mem::zeroed() };
+            if unsafe { libc::sigaction(signal, &action, &mut old_action) } == 0 {
+                previous.push((signal, old_action));
+            }
+        }
+        CrashGuard { previous }
+    }
+
+    // The crash which terminated the test thread, if any
+    pub fn take_crash() -> Option<CrashInfo> {
+        let signal = CRASH_SIGNAL.swap(0, Ordering::SeqCst);
+        if signal == 0 {
+            return None;
+        }
+        let address = match CRASH_HAS_ADDRESS.load(Ordering::SeqCst) {
+            true => Some(CRASH_ADDRESS.load(Ordering::SeqCst)),
+            false => None,
+        };
+        let frames : Vec<usize> = CRASH_FRAMES[..CRASH_NUM_FRAMES.load(Ordering::SeqCst)]
+            .iter()
+            .map(|frame| frame.load(Ordering::SeqCst))
+            .collect();
+        Some(CrashInfo { signal, address, frames : test_frames(&frames, true).to_vec() })
+    }
+}
+
+impl Drop for CrashGuard {
+    fn drop(&mut self) {
+        for (signal, action) in &self.previous {
+            unsafe { libc::sigaction(*signal, action, ptr::null_mut()) };
+        }
+    }
+}
+
+//
+// Marks the calling thread as the test thread and gives it an alternate signal stack, undone when dropped
+// note: also dropped when the thread is terminated through 'pthread_exit'
+//
+pub struct TestThreadGuard {
+    stack : *mut c_void,
+}
+
+impl TestThreadGuard {
+    pub fn enter() -> TestThreadGuard {
+        let stack = unsafe {
+            libc::mmap(ptr::null_mut(), ALT_STACK_SIZE, libc::PROT_READ | libc::PROT_WRITE,
+                       libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+        };
+        if stack == libc::MAP_FAILED {
+            println!("ERR: Unable to allocate signal stack, crashes on the test thread will not be caught");
+            return TestThreadGuard { stack : ptr::null_mut() };
+        }
+        let signal_stack = libc::stack_t {
+            ss_sp : stack,
+            ss_flags : 0,
+            ss_size : ALT_STACK_SIZE,
+        };
+        unsafe { libc::sigaltstack(&signal_stack, ptr::null_mut()) };
+        ON_TEST_THREAD.with(|on_test_thread| on_test_thread.set(true));
+        TestThreadGuard { stack }
+    }
+}
+
+impl Drop for TestThreadGuard {
+    fn drop(&mut self) {
+        if self.stack.is_null() {
+            return;
+        }
+        ON_TEST_THREAD.with(|on_test_thread| on_test_thread.set(false));
+        let signal_stack = libc::stack_t {
+            ss_sp : ptr::null_mut(),
+            ss_flags : libc::SS_DISABLE,
+            ss_size : 0,
+        };
+        unsafe {
+            libc::sigaltstack(&signal_stack, ptr::null_mut());
+            libc::munmap(self.stack, ALT_STACK_SIZE);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::{self, Path, PathBuf};
+use crate::test_runner::{Config, Singleton};
+
+//
+// Resolves the test data directory from the '--data-dir' template
+// Supported placeholders:
+//  {libdir}  - directory of the library under test
+//  {libname} - file name of the library without extension
+//  {module}  - name of the module being executed (empty for globals)
+//
+// The result is absolute, so it is valid regardless of the working directory of the test.
+//
+pub fn resolve_data_dir(library : &str, module : &str) -> PathBuf {
+    let lib_path = Path::new(library);
+    let lib_dir = match lib_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().to_string(),
+        _ => ".".to_string(),
+    };
+    let lib_name = lib_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+
+    let data_dir = Config::instance().data_dir
+        .replace("{libdir}", &lib_dir)
+        .replace("{libname}", &lib_name)
+        .replace("{module}", module);
+
+    let data_dir = PathBuf::from(data_dir);
+    path::absolute(&data_dir).unwrap_or(data_dir)
+}
@@ -0,0 +1,148 @@
+use std::ffi::c_int;
+use std::fmt;
+use crate::test_runner::{signal_name, ChildOutcome};
+
+// Cases named 'test_<module>_death_<case>' are death tests expecting any abnormal termination
+pub const DEATH_CASE_PREFIX : &str = "death_";
+
+//
+// How a death test is expected to terminate, declared through 'expect_death' or implied by the case name
+// Without a signal or exit status any signal or non-zero exit status is accepted.
+//
+#[derive(Debug, Clone, Default)]
+pub struct DeathExpectation {
+    pub signal : Option<c_int>,
+    pub exit_status : Option<c_int>,
+    // Wildcard pattern ('*' and '?') searched for in the stderr output of the case
+    pub stderr_pattern : Option<String>,
+}
+
+// Death expectation for a case, as declared by the module main
+#[derive(Debug, Clone)]
+pub struct CaseDeathExpectation {
+    pub case : String,
+    pub expectation : DeathExpectation,
+}
+
+impl DeathExpectation {
+    // From the 'expect_death' arguments, negative numbers means 'any'
+    pub fn new(signal : c_int, exit_status : c_int, stderr_pattern : Option<&str>) -> DeathExpectation {
+        Self {
+            signal : (signal > 0).then_some(signal),
+            exit_status : (exit_status >= 0).then_some(exit_status),
+            stderr_pattern : stderr_pattern.filter(|pattern| !pattern.is_empty()).map(|pattern| pattern.to_string()),
+        }
+    }
+
+    // Ok if the child terminated as expected, otherwise the reason
+    pub fn check(&self, outcome : &ChildOutcome, stderr : &str) -> Result<(), String> {
+        let died_as_expected = match outcome {
+            ChildOutcome::Crashed { signal, .. } | ChildOutcome::Signaled { signal } => match (self.signal, self.exit_status) {
+                (None, None) => true,
+                (expected, _) => expected == Some(*signal),
+            },
+            ChildOutcome::Exited { status } => match (self.signal, self.exit_status) {
+                (None, None) => *status != 0,
+                (_, expected) => expected == Some(*status),
+            },
+            ChildOutcome::Completed(_) => false,
+        };
+        if !died_as_expected {
+            return Err(format!("expected {}, but the case {}", self, describe_outcome(outcome)));
+        }
+
+        if let Some(pattern) = &self.stderr_pattern {
+            if !wildcard_match(&format!("*{}*", pattern), stderr) {
+                return Err(format!("stderr does not match '{}', stderr was '{}'", pattern, stderr.trim_end()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DeathExpectation {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.signal, self.exit_status) {
+            (Some(signal), Some(status)) => write!(f, "termination by {} or exit status {}", signal_name(signal), status),
+            (Some(signal), None) => write!(f, "termination by {}", signal_name(signal)),
+            (None, Some(status)) => write!(f, "exit status {}", status),
+            (None, None) => write!(f, "termination by a signal or a non-zero exit status"),
+        }
+    }
+}
+
+fn describe_outcome(outcome : &ChildOutcome) -> String {
+    match outcome {
+        ChildOutcome::Crashed { signal, .. } | ChildOutcome::Signaled { signal } => format!("was terminated by {}", signal_name(*signal)),
+        ChildOutcome::Exited { status } => format!("exited with status {}", status),
+        ChildOutcome::Completed(_) => "returned".to_string(),
+    }
+}
+
+// '*' matches any sequence (including none), '?' any single character
+pub fn wildcard_match(pattern : &str, text : &str) -> bool {
+    let pattern : Vec<char> = pattern.chars().collect();
+    let text : Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last '*' and the text position it currently covers up to
+    let mut backtrack : Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last '*' cover one more character and try again
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_match_literal() {
+        assert!(wildcard_match("", ""));
+        assert!(wildcard_match("abort", "abort"));
+        assert!(!wildcard_match("abort", "abor"));
+        assert!(!wildcard_match("abor", "abort"));
+        assert!(!wildcard_match("", "a"));
+    }
+
+    #[test]
+    fn wildcard_match_question_mark() {
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(!wildcard_match("a?c", "ac"));
+        assert!(!wildcard_match("?", ""));
+    }
+
+    #[test]
+    fn wildcard_match_star() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("**", "a"));
+        assert!(wildcard_match("*failed*", "assertion failed: x > 0"));
+        assert!(wildcard_match("a*c", "ac"));
+        assert!(!wildcard_match("a*c", "ab"));
+        assert!(wildcard_match("*.c:*", "file.c:42"));
+    }
+
+    #[test]
+    fn wildcard_match_backtracks() {
+        // The first 'b' covered by '*' is not the one to match on
+        assert!(wildcard_match("*bc", "abbc"));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("a*b*c", "aXbYbZ"));
+        assert!(wildcard_match("*a?", "aaab"));
+    }
+}
@@ -0,0 +1,74 @@
+use std::ffi::{c_int, c_void, CStr};
+use std::io::{self, Write};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::test_runner::*;
+
+//
+// 'exit' and '_exit' called by the code under test
+// The runner interposes both; called on the test thread only the thread is terminated and the case fails with the
+// exit status. Called from any other thread while a case is running there is no way to continue, the running case is
+// reported as failed together with a partial report of the run before the process exits.
+//
+
+// Only set while a test function is executing (see TestFunction::execute), any other exit - the runner's own or from
+// a program just linking the library - goes straight to libc. Cleared where exiting is the expected outcome (death
+// tests) or done by the runner it-self (child processes).
+static INTERCEPT_EXIT : AtomicBool = AtomicBool::new(false);
+
+pub fn set_exit_interception(enabled : bool) {
+    INTERCEPT_EXIT.store(enabled, Ordering::SeqCst);
+}
+
+#[no_mangle]
+extern "C-unwind" fn exit(status : c_int) -> ! {
+    intercept_exit("exit", status);
+    let real_exit : extern "C" fn(c_int) -> ! = unsafe { mem::transmute(next_symbol(c"exit")) };
+    real_exit(status)
+}
+
+#[no_mangle]
+extern "C-unwind" fn _exit(status : c_int) -> ! {
+    intercept_exit("_exit", status);
+    let real_exit : extern "C" fn(c_int) -> ! = unsafe { mem::transmute(next_symbol(c"_exit")) };
+    real_exit(status)
+}
+
+#[no_mangle]
+extern "C-unwind" fn _Exit(status : c_int) -> ! {
+    _exit(status)
+}
+
+// The libc implementation of a symbol interposed by the runner
+fn next_symbol(name : &CStr) -> *mut c_void {
+    let sym = unsafe { libc::dlsym(libc::RTLD_NEXT, name.as_ptr()) };
+    if sym.is_null() {
+        // Nothing sensible left to do, skip all at-exit handling
+        unsafe { libc::syscall(libc::SYS_exit_group, 1) };
+    }
+    sym
+}
+
+// Returns if the process should exit as requested
+fn intercept_exit(func : &str, status : c_int) {
+    if !INTERCEPT_EXIT.load(Ordering::SeqCst) {
+        return;
+    }
+
+    if is_runner_thread(unsafe { libc::gettid() }) {
+        let func_error = TestFuncError::new(TestFuncErrorClass::Fatal, "", 0, &format!("{}({}) called by the test", func, status))
+            .with_backtrace(symbolize(&capture_test_frames()));
+        func_error.print();
+        CONTEXT.lock().unwrap().func_error = Some(func_error);
+        exit_test_thread();
+    }
+
+    // Not the test thread, report the running case (if any) before the process goes down
+    // note: 'try_lock', exit could have been called while the journal is being updated
+    if let Ok(journal) = RUN_JOURNAL.try_lock() {
+        if journal.running_case().is_some() {
+            journal.print_partial_report(&format!("{}({}) called outside the test thread, terminating", func, status));
+            io::stdout().flush().ok();
+        }
+    }
+}
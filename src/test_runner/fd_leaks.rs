@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+use std::{fs, io};
+use crate::test_runner::CheckIssue;
+
+//
+// Open file descriptors of the process and what they point to, see --check-fd-leaks
+//
+pub struct FdSnapshot {
+    fds : BTreeMap<i32, String>,
+}
+
+impl FdSnapshot {
+    pub fn take() -> io::Result<FdSnapshot> {
+        // Collect the numbers first, the descriptor used for reading the directory is closed before resolving
+        // the targets - so it drops out by it-self
+        let numbers : Vec<i32> = fs::read_dir("/proc/self/fd")?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok()))
+            .collect();
+
+        let mut fds = BTreeMap::new();
+        for fd in numbers {
+            if let Ok(target) = fs::read_link(format!("/proc/self/fd/{}", fd)) {
+                fds.insert(fd, target.to_string_lossy().to_string());
+            }
+        }
+        Ok(FdSnapshot { fds })
+    }
+
+    // Descriptors open now which were not open (or pointed elsewhere) in 'before'
+    pub fn opened_since(&self, before : &FdSnapshot) -> Vec<(i32, &str)> {
+        self.fds
+            .iter()
+            .filter(|(fd, target)| before.fds.get(fd) != Some(target))
+            .map(|(fd, target)| (*fd, target.as_str()))
+            .collect()
+    }
+
+    // Compare with a snapshot taken before the test, one issue per leaked descriptor
+    pub fn check_leaks(before : &FdSnapshot, is_failure : bool) -> Vec<CheckIssue> {
+        let after = match FdSnapshot::take() {
+            Ok(after) => after,
+            Err(err) => {
+                println!("ERR: Unable to read open file descriptors: {}", err);
+                return Vec::new();
+            }
+        };
+        after.opened_since(before)
+            .iter()
+            .map(|(fd, target)| CheckIssue::new("fd-leak", &format!("fd {} still open -> {}", fd, target), is_failure))
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::ffi::{CStr, OsString};
+use std::path::PathBuf;
+use std::{mem, ptr};
+use libc::{c_int, mode_t};
+use crate::test_runner::{signal_name, CheckIssue};
+
+//
+// Process wide state a test can change behind the back of the following tests, see --check-global-state
+//
+pub struct GlobalStateSnapshot {
+    env : BTreeMap<OsString, OsString>,
+    cwd : Option<PathBuf>,
+    umask : mode_t,
+    signals : BTreeMap<c_int, SignalDisposition>,
+    locale : Option<String>,
+}
+
+#[derive(PartialEq)]
+struct SignalDisposition {
+    handler : libc::sighandler_t,
+    flags : c_int,
+}
+
+impl SignalDisposition {
+    fn describe(&self) -> String {
+        match self.handler {
+            libc::SIG_DFL => "default".to_string(),
+            libc::SIG_IGN => "ignored".to_string(),
+            handler => format!("handler {:#x}", handler),
+        }
+    }
+}
+
+impl GlobalStateSnapshot {
+    pub fn take() -> GlobalStateSnapshot {
+        GlobalStateSnapshot {
+            env : env::vars_os().collect(),
+            cwd : env::current_dir().ok(),
+            umask : current_umask(),
+            signals : signal_dispositions(),
+            locale : current_locale(),
+        }
+    }
+
+    // Everything which differs from 'before', one line per change
+    pub fn changes_since(&self, before : &GlobalStateSnapshot) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        let names : BTreeSet<&OsString> = before.env.keys().chain(self.env.keys()).collect();
+        for name in names {
+            let name_str = name.to_string_lossy();
+            match (before.env.get(name), self.env.get(name)) {
+                (None, Some(value)) => changes.push(format!("environment variable {} set to '{}'", name_str, value.to_string_lossy())),
+                (Some(value), None) => changes.push(format!("environment variable {} removed, was '{}'", name_str, value.to_string_lossy())),
+                (Some(old), Some(new)) if old != new => {
+                    changes.push(format!("environment variable {} changed from '{}' to '{}'", name_str, old.to_string_lossy(), new.to_string_lossy()));
+                },
+                _ => (),
+            }
+        }
+
+        if before.cwd != self.cwd {
+            changes.push(format!("working directory changed from {} to {}", describe_path(&before.cwd), describe_path(&self.cwd)));
+        }
+        if before.umask != self.umask {
+            changes.push(format!("umask changed from {:03o} to {:03o}", before.umask, self.umask));
+        }
+        for (signal, disposition) in &self.signals {
+            match before.signals.get(signal) {
+                Some(old) if old != disposition => {
+                    let change = match old.handler == disposition.handler {
+                        true => format!("flags changed from {:#x} to {:#x}", old.flags, disposition.flags),
+                        false => format!("changed from {} to {}", old.describe(), disposition.describe()),
+                    };
+                    changes.push(format!("disposition of {} {}", signal_name(*signal), change));
+                },
+                _ => (),
+            }
+        }
+        if before.locale != self.locale {
+            changes.push(format!("locale changed from {} to {}", describe_locale(&before.locale), describe_locale(&self.locale)));
+        }
+        changes
+    }
+
+    // Compare with a snapshot taken before the test, one issue per change
+    pub fn check_changes(before : &GlobalStateSnapshot, is_failure : bool) -> Vec<CheckIssue> {
+        GlobalStateSnapshot::take()
+            .changes_since(before)
+            .iter()
+            .map(|change| CheckIssue::new("global-state", change, is_failure))
+            .collect()
+    }
+}
+
+// There is no way to read the umask without setting it
+fn current_umask() -> mode_t {
+    unsafe {
+        let mask = libc::umask(0o022);
+        libc::umask(mask);
+        mask
+    }
+}
+
+fn signal_dispositions() -> BTreeMap<c_int, SignalDisposition> {
+    let mut signals = BTreeMap::new();
+    for signal in 1..=libc::SIGRTMAX() {
+        if signal == libc::SIGKILL || signal == libc::SIGSTOP {
+            continue;
+        }
+        let mut action : libc::sigaction = unsafe { mem::zeroed() };
+        if unsafe { libc::sigaction(signal, ptr::null(), &mut action) } == 0 {
+            signals.insert(signal, SignalDisposition { handler : action.sa_sigaction, flags : action.sa_flags });
+        }
+    }
+    signals
+}
+
+fn current_locale() -> Option<String> {
+    let locale = unsafe { libc::setlocale(libc::LC_ALL, ptr::null()) };
+    if locale.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(locale) }.to_string_lossy().to_string())
+}
+
+fn describe_path(path : &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => "<removed>".to_string(),
+    }
+}
+
+fn describe_locale(locale : &Option<String>) -> &str {
+    locale.as_deref().unwrap_or("<unknown>")
+}
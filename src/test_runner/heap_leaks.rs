@@ -0,0 +1,427 @@
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+use std::ffi::{c_int, c_void};
+use std::sync::{Mutex, Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{mem, ptr};
+use crate::test_runner::{capture_frames, prepare_backtrace, symbolize, test_frames, CheckIssue};
+
+//
+// Heap leak detection, see --check-heap-leaks - only built with the 'heap-leak-check' feature
+// The runner interposes the C allocator (malloc/free and friends) for the whole process, while tracking is active
+// every allocation made through it is recorded in a side table and removed again when freed.
+// Whatever is left in the table when the case is done has leaked.
+//
+// The runner's own (Rust) allocations goes directly to the libc allocator, so they never show up as leaks.
+//
+
+extern "C" {
+    fn __libc_malloc(size : usize) -> *mut c_void;
+    fn __libc_calloc(nmemb : usize, size : usize) -> *mut c_void;
+    fn __libc_realloc(p : *mut c_void, size : usize) -> *mut c_void;
+    fn __libc_memalign(align : usize, size : usize) -> *mut c_void;
+    fn __libc_free(p : *mut c_void);
+    static mut stdout : *mut libc::FILE;
+}
+
+// Alignment guaranteed by malloc on the platforms we support
+const MIN_ALIGN : usize = 16;
+const MAX_FRAMES : usize = 16;
+// Leaked blocks reported with backtraces, the rest are only counted
+const MAX_REPORTED_BLOCKS : usize = 10;
+
+static TRACKING : AtomicBool = AtomicBool::new(false);
+static CAPTURE_BACKTRACES : AtomicBool = AtomicBool::new(false);
+static BLOCKS : Mutex<BlockTable> = Mutex::new(BlockTable::new());
+
+thread_local! {
+    // Set while inside the tracker, allocations made by the tracker it-self (backtrace) are not tracked
+    static IN_TRACKER : Cell<bool> = const { Cell::new(false) };
+}
+
+//
+// Allocator for the runner it-self, bypasses the interposed functions
+//
+struct RunnerAllocator;
+
+unsafe impl GlobalAlloc for RunnerAllocator {
+    unsafe fn alloc(&self, layout : Layout) -> *mut u8 {
+        if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+            __libc_malloc(layout.size()) as *mut u8
+        } else {
+            __libc_memalign(layout.align(), layout.size()) as *mut u8
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout : Layout) -> *mut u8 {
+        if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+            __libc_calloc(1, layout.size()) as *mut u8
+        } else {
+            let p = self.alloc(layout);
+            if !p.is_null() {
+                ptr::write_bytes(p, 0, layout.size());
+            }
+            p
+        }
+    }
+
+    unsafe fn dealloc(&self, p : *mut u8, _layout : Layout) {
+        __libc_free(p as *mut c_void);
+    }
+
+    unsafe fn realloc(&self, p : *mut u8, layout : Layout, new_size : usize) -> *mut u8 {
+        if layout.align() <= MIN_ALIGN && layout.align() <= new_size {
+            return __libc_realloc(p as *mut c_void, new_size) as *mut u8;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_p = self.alloc(new_layout);
+        if !new_p.is_null() {
+            ptr::copy_nonoverlapping(p, new_p, layout.size().min(new_size));
+            self.dealloc(p, layout);
+        }
+        new_p
+    }
+}
+
+#[global_allocator]
+static RUNNER_ALLOCATOR : RunnerAllocator = RunnerAllocator;
+
+//
+// Interposed C allocator functions, exported from the runner executable they replace the libc versions
+//
+#[no_mangle]
+extern "C" fn malloc(size : usize) -> *mut c_void {
+    let p = unsafe { __libc_malloc(size) };
+    track_alloc(p, size);
+    p
+}
+
+#[no_mangle]
+extern "C" fn calloc(nmemb : usize, size : usize) -> *mut c_void {
+    let p = unsafe { __libc_calloc(nmemb, size) };
+    track_alloc(p, nmemb.saturating_mul(size));
+    p
+}
+
+#[no_mangle]
+extern "C" fn realloc(p : *mut c_void, size : usize) -> *mut c_void {
+    let new_p = unsafe { __libc_realloc(p, size) };
+    // realloc(p, 0) frees 'p', on any other failure 'p' is left untouched
+    if new_p.is_null() && size != 0 {
+        return new_p;
+    }
+    track_realloc(p, new_p, size);
+    new_p
+}
+
+#[no_mangle]
+extern "C" fn free(p : *mut c_void) {
+    track_free(p);
+    unsafe { __libc_free(p) };
+}
+
+#[no_mangle]
+extern "C" fn memalign(align : usize, size : usize) -> *mut c_void {
+    let p = unsafe { __libc_memalign(align, size) };
+    track_alloc(p, size);
+    p
+}
+
+#[no_mangle]
+extern "C" fn aligned_alloc(align : usize, size : usize) -> *mut c_void {
+    memalign(align, size)
+}
+
+#[no_mangle]
+extern "C" fn posix_memalign(memptr : *mut *mut c_void, align : usize, size : usize) -> c_int {
+    if !align.is_power_of_two() || !align.is_multiple_of(mem::size_of::<*mut c_void>()) {
+        return libc::EINVAL;
+    }
+    let p = memalign(align, size);
+    if p.is_null() {
+        return libc::ENOMEM;
+    }
+    unsafe { *memptr = p };
+    0
+}
+
+// Runs 'f' unless tracking is disabled or the tracker is already active on this thread
+fn with_tracker(f : impl FnOnce()) {
+    if !TRACKING.load(Ordering::Relaxed) {
+        return;
+    }
+    let _ = IN_TRACKER.try_with(|in_tracker| {
+        if in_tracker.get() {
+            return;
+        }
+        in_tracker.set(true);
+        f();
+        in_tracker.set(false);
+    });
+}
+
+fn track_alloc(p : *mut c_void, size : usize) {
+    if p.is_null() {
+        return;
+    }
+    with_tracker(|| {
+        let block = Block::new(p, size);
+        if let Ok(mut table) = BLOCKS.lock() {
+            table.insert(block);
+        }
+    });
+}
+
+fn track_realloc(p : *mut c_void, new_p : *mut c_void, size : usize) {
+    with_tracker(|| {
+        let Ok(mut table) = BLOCKS.lock() else {
+            return;
+        };
+        let was_tracked = !p.is_null() && table.remove(p as usize).is_some();
+        // Only blocks allocated by the case are tracked, growing an older block doesn't make it a leak
+        if !new_p.is_null() && (p.is_null() || was_tracked) {
+            drop(table);
+            track_block(new_p, size);
+        }
+    });
+}
+
+fn track_block(p : *mut c_void, size : usize) {
+    let block = Block::new(p, size);
+    if let Ok(mut table) = BLOCKS.lock() {
+        table.insert(block);
+    }
+}
+
+fn track_free(p : *mut c_void) {
+    if p.is_null() {
+        return;
+    }
+    with_tracker(|| {
+        if let Ok(mut table) = BLOCKS.lock() {
+            table.remove(p as usize);
+        }
+    });
+}
+
+//
+// A tracked allocation
+//
+#[derive(Clone, Copy)]
+struct Block {
+    addr : usize,
+    size : usize,
+    num_frames : usize,
+    frames : [usize; MAX_FRAMES],
+}
+
+// Values of 'Block::addr' which can't be real allocations
+const SLOT_EMPTY : usize = 0;
+const SLOT_REMOVED : usize = 1;
+
+impl Block {
+    fn new(p : *mut c_void, size : usize) -> Block {
+        let mut block = Block {
+            addr : p as usize,
+            size,
+            num_frames : 0,
+            frames : [0; MAX_FRAMES],
+        };
+        if CAPTURE_BACKTRACES.load(Ordering::Relaxed) {
+            block.num_frames = capture_frames(&mut block.frames);
+        }
+        block
+    }
+}
+
+//
+// Open addressing hash table of tracked blocks
+// The memory is mapped directly, the table can't allocate through the allocator it is tracking.
+//
+struct BlockTable {
+    slots : *mut Block,
+    capacity : usize,
+    // Slots in use, including removed ones
+    used : usize,
+}
+
+// Only ever accessed through the 'BLOCKS' mutex
+unsafe impl Send for BlockTable {}
+
+impl BlockTable {
+    const fn new() -> BlockTable {
+        BlockTable {
+            slots : ptr::null_mut(),
+            capacity : 0,
+            used : 0,
+        }
+    }
+
+    fn slot_index(&self, addr : usize) -> usize {
+        ((addr >> 4).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 20) & (self.capacity - 1)
+    }
+
+    fn slot(&mut self, index : usize) -> &mut Block {
+        unsafe { &mut *self.slots.add(index) }
+    }
+
+    fn insert(&mut self, block : Block) {
+        if (self.used + 1) * 2 > self.capacity && !self.grow() {
+            return;
+        }
+        let mut index = self.slot_index(block.addr);
+        let mut free_index = None;
+        loop {
+            let addr = self.slot(index).addr;
+            if addr == block.addr || addr == SLOT_EMPTY {
+                break;
+            }
+            if addr == SLOT_REMOVED && free_index.is_none() {
+                free_index = Some(index);
+            }
+            index = (index + 1) & (self.capacity - 1);
+        }
+        let addr = self.slot(index).addr;
+        match free_index {
+            Some(free_index) if addr != block.addr => *self.slot(free_index) = block,
+            _ => {
+                if addr == SLOT_EMPTY {
+                    self.used += 1;
+                }
+                *self.slot(index) = block;
+            },
+        }
+    }
+
+    fn remove(&mut self, addr : usize) -> Option<Block> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut index = self.slot_index(addr);
+        loop {
+            let block = *self.slot(index);
+            if block.addr == addr {
+                self.slot(index).addr = SLOT_REMOVED;
+                return Some(block);
+            }
+            if block.addr == SLOT_EMPTY {
+                return None;
+            }
+            index = (index + 1) & (self.capacity - 1);
+        }
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = &Block> {
+        (0..self.capacity)
+            .map(|index| unsafe { &*self.slots.add(index) })
+            .filter(|block| block.addr != SLOT_EMPTY && block.addr != SLOT_REMOVED)
+    }
+
+    fn clear(&mut self) {
+        if self.capacity > 0 {
+            unsafe { ptr::write_bytes(self.slots, 0, self.capacity) };
+        }
+        self.used = 0;
+    }
+
+    // Resizes the table for the live blocks (dropping removed slots) and rehashes them
+    fn grow(&mut self) -> bool {
+        let live : Vec<Block> = self.blocks().copied().collect();
+        let capacity = (live.len() * 4).max(4096).next_power_of_two();
+        let slots = unsafe {
+            libc::mmap(ptr::null_mut(), capacity * mem::size_of::<Block>(), libc::PROT_READ | libc::PROT_WRITE,
+                       libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+        };
+        if slots == libc::MAP_FAILED {
+            return false;
+        }
+        if !self.slots.is_null() {
+            unsafe { libc::munmap(self.slots as *mut c_void, self.capacity * mem::size_of::<Block>()) };
+        }
+        self.slots = slots as *mut Block;
+        self.capacity = capacity;
+        self.used = 0;
+        for block in live {
+            self.insert(block);
+        }
+        true
+    }
+}
+
+//
+// Allocations made while tracking which were never freed
+//
+pub struct LeakedBlock {
+    pub size : usize,
+    pub frames : Vec<usize>,
+}
+
+pub struct HeapTracker;
+
+impl HeapTracker {
+    // Start tracking a new case, anything tracked so far is dropped
+    pub fn start(capture_backtraces : bool) {
+        static PREPARE : Once = Once::new();
+        PREPARE.call_once(Self::allocate_lazy_state);
+
+        CAPTURE_BACKTRACES.store(capture_backtraces, Ordering::Relaxed);
+        BLOCKS.lock().unwrap().clear();
+        TRACKING.store(true, Ordering::SeqCst);
+    }
+
+    // Runs 'f' without tracking allocations made on the calling thread, used for the runner's own work while tracking
+    pub fn untracked<R>(f : impl FnOnce() -> R) -> R {
+        let was_in_tracker = IN_TRACKER.with(|in_tracker| in_tracker.replace(true));
+        let res = f();
+        IN_TRACKER.with(|in_tracker| in_tracker.set(was_in_tracker));
+        res
+    }
+
+    // The C library allocates some state on first use and keeps it, do that up front so it isn't blamed on a test
+    fn allocate_lazy_state() {
+        // The first unwind (backtrace, pthread_exit from an assert) loads the unwinder
+        prepare_backtrace();
+
+        // stdout gets its buffer on first output, a zero length write doesn't allocate it. Without a buffer given,
+        // setvbuf allocates the same one the first output would and the mode is put back to what that output would
+        // pick (line buffered on a terminal), so the code under test sees no difference.
+        // note: this runs before any test code, nothing has configured or used stdout yet
+        unsafe {
+            libc::setvbuf(stdout, ptr::null_mut(), libc::_IOFBF, 0);
+            if libc::isatty(libc::STDOUT_FILENO) != 0 {
+                libc::setvbuf(stdout, ptr::null_mut(), libc::_IOLBF, 0);
+            }
+        }
+    }
+
+    pub fn stop() -> Vec<LeakedBlock> {
+        TRACKING.store(false, Ordering::SeqCst);
+        let table = BLOCKS.lock().unwrap();
+        table.blocks()
+            .map(|block| LeakedBlock {
+                size : block.size,
+                frames : block.frames[..block.num_frames].to_vec(),
+            })
+            .collect()
+    }
+
+    // Stop tracking, one issue with the totals and one per leaked block when backtraces are captured
+    pub fn check_leaks(is_failure : bool) -> Vec<CheckIssue> {
+        let mut leaked = Self::stop();
+        if leaked.is_empty() {
+            return Vec::new();
+        }
+        let bytes : usize = leaked.iter().map(|block| block.size).sum();
+        let mut issues = vec![CheckIssue::new("heap-leak", &format!("{} bytes in {} blocks still allocated", bytes, leaked.len()), is_failure)];
+
+        leaked.sort_by_key(|block| std::cmp::Reverse(block.size));
+        for block in leaked.iter().filter(|block| !block.frames.is_empty()).take(MAX_REPORTED_BLOCKS) {
+            let mut message = format!("{} bytes allocated at", block.size);
+            for line in symbolize(test_frames(&block.frames, false)) {
+                message += &format!("\n      {}", line);
+            }
+            issues.push(CheckIssue::new("heap-leak", &message, is_failure));
+        }
+        issues
+    }
+}
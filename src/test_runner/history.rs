@@ -0,0 +1,191 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::test_runner::{canonical_library_name, ResourceUsage};
+
+//
+// Run history, each run is stored as a JSON file 'run-<unix time ms>.json' in the history directory.
+// Cases are keyed by library and symbol (the symbol includes module, case and any parameter set).
+//
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseRecord {
+    pub library : String,
+    pub module : String,
+    pub case : String,
+    pub symbol : String,
+    pub outcome : String,
+    pub duration_sec : f64,     // Mean duration if the case was executed several times
+    pub runs : u32,
+    #[serde(default)]
+    pub resource_usage : Option<ResourceUsage>,     // From the last execution
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub timestamp_ms : u64,
+    pub cases : Vec<CaseRecord>,
+}
+
+pub struct History {
+    dir : PathBuf,
+}
+
+impl CaseRecord {
+    pub fn new(library : &str, module : &str, case : &str, symbol : &str, outcome : &str, duration_sec : f64) -> CaseRecord {
+        Self {
+            library : canonical_library_name(library),
+            module : module.to_string(),
+            case : case.to_string(),
+            symbol : symbol.to_string(),
+            outcome : outcome.to_string(),
+            duration_sec,
+            runs : 1,
+            resource_usage : None,
+        }
+    }
+
+    pub fn did_fail(&self) -> bool {
+        self.outcome == "fail"
+    }
+
+    pub fn is_same_case(&self, other : &CaseRecord) -> bool {
+        self.library == other.library && self.symbol == other.symbol
+    }
+}
+
+impl RunRecord {
+    pub fn new() -> RunRecord {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default();
+        Self {
+            timestamp_ms,
+            cases : Vec::new(),
+        }
+    }
+
+    // Add a case, executing a case several times (see --repeat) keeps a single record with the mean duration
+    // and the case is considered failed if any of the executions failed
+    pub fn add(&mut self, record : CaseRecord) {
+        match self.cases.iter_mut().find(|c| c.is_same_case(&record)) {
+            Some(existing) => {
+                existing.duration_sec = (existing.duration_sec * existing.runs as f64 + record.duration_sec) / (existing.runs + 1) as f64;
+                existing.runs += 1;
+                if record.did_fail() {
+                    existing.outcome = record.outcome;
+                }
+                existing.resource_usage = record.resource_usage;
+            },
+            None => self.cases.push(record),
+        }
+    }
+
+    pub fn find(&self, record : &CaseRecord) -> Option<&CaseRecord> {
+        self.cases.iter().find(|c| c.is_same_case(record))
+    }
+}
+
+impl History {
+    pub fn new(dir : &str) -> History {
+        Self {
+            dir : PathBuf::from(dir),
+        }
+    }
+
+    // Returns the most recent run, if any
+    pub fn latest(&self) -> io::Result<Option<RunRecord>> {
+        match self.run_files()?.last() {
+            Some(filename) => Self::load(filename).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Returns all stored runs, oldest first
+    pub fn runs(&self) -> io::Result<Vec<RunRecord>> {
+        self.run_files()?.iter().map(|filename| Self::load(filename)).collect()
+    }
+
+    pub fn save(&self, run : &RunRecord) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let filename = self.dir.join(format!("run-{:016}.json", run.timestamp_ms));
+        let mut writer = BufWriter::new(File::create(filename)?);
+        serde_json::to_writer_pretty(&mut writer, run)?;
+        writer.flush()
+    }
+
+    // Remove the oldest runs, keeping at most 'max_runs'
+    pub fn prune(&self, max_runs : usize) -> io::Result<()> {
+        let files = self.run_files()?;
+        if files.len() > max_runs {
+            for filename in &files[..files.len() - max_runs] {
+                fs::remove_file(filename)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(filename : &Path) -> io::Result<RunRecord> {
+        let reader = BufReader::new(File::open(filename)?);
+        let run = serde_json::from_reader(reader)?;
+        Ok(run)
+    }
+
+    // Run files sorted oldest to newest, the timestamp is zero padded so sorting by name works
+    fn run_files(&self) -> io::Result<Vec<PathBuf>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut files : Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                name.starts_with("run-") && name.ends_with(".json")
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(library : &str, symbol : &str, outcome : &str, duration_sec : f64) -> CaseRecord {
+        CaseRecord::new(library, "a", "x", symbol, outcome, duration_sec)
+    }
+
+    #[test]
+    fn add_new_cases() {
+        let mut run = RunRecord::new();
+        run.add(record("libA.so", "test_a_x", "pass", 1.0));
+        run.add(record("libA.so", "test_a_y", "pass", 2.0));
+        // Same symbol in another library is another case
+        run.add(record("libB.so", "test_a_x", "fail", 3.0));
+        assert_eq!(run.cases.len(), 3);
+        assert!(run.cases.iter().all(|c| c.runs == 1));
+        assert_eq!(run.find(&record("libB.so", "test_a_x", "pass", 0.0)).unwrap().duration_sec, 3.0);
+        assert!(run.find(&record("libC.so", "test_a_x", "pass", 0.0)).is_none());
+    }
+
+    #[test]
+    fn add_repeated_case_keeps_mean_duration() {
+        let mut run = RunRecord::new();
+        run.add(record("libA.so", "test_a_x", "pass", 1.0));
+        run.add(record("libA.so", "test_a_x", "pass", 2.0));
+        run.add(record("libA.so", "test_a_x", "pass", 6.0));
+        assert_eq!(run.cases.len(), 1);
+        assert_eq!(run.cases[0].runs, 3);
+        assert_eq!(run.cases[0].duration_sec, 3.0);
+    }
+
+    #[test]
+    fn add_repeated_case_failing_once_is_failed() {
+        let mut run = RunRecord::new();
+        run.add(record("libA.so", "test_a_x", "pass", 1.0));
+        run.add(record("libA.so", "test_a_x", "fail", 1.0));
+        run.add(record("libA.so", "test_a_x", "pass", 1.0));
+        assert_eq!(run.cases.len(), 1);
+        assert!(run.cases[0].did_fail());
+    }
+}
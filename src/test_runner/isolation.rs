@@ -0,0 +1,395 @@
+use std::ffi::c_int;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::fd::FromRawFd;
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+use std::time::Duration;
+use std::{fmt, mem};
+use serde::{Deserialize, Serialize};
+use crate::test_runner::{capture_frames, prepare_backtrace, set_exit_interception, test_frames, CheckIssue, Config, ResourceUsage, Singleton, TestFuncError, TestReturnCode, MAX_BACKTRACE_FRAMES};
+
+// Crashes are reported as 'CRASH <signal> <vm peak kb> <frame>...' on the result pipe, anything else is a JSON encoded 'ChildResult'
+const CRASH_TAG : &[u8] = b"CRASH ";
+
+// Peak virtual memory this close to the address space limit is considered a violation
+const AS_LIMIT_MARGIN : f64 = 0.9;
+
+// Write end of the result pipe in the child, used by the crash handler
+static RESULT_FD: AtomicI32 = AtomicI32::new(-1);
+// Address space inherited from the runner, see 'ChildBaseline' - used by the crash handler
+static VM_BASE_KB: AtomicI64 = AtomicI64::new(0);
+
+//
+// Per case resource limits, these are only applied when executing in a child process (see --isolate)
+// The limits are on top of what the child inherits from the runner, the usage reported back is the child's own.
+//
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    pub address_space_mb : Option<u64>,
+    pub cpu_sec : Option<u64>,
+    pub open_files : Option<u64>,
+    pub core_mb : Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LimitViolation {
+    AddressSpace { limit_mb : u64, peak_mb : u64 },
+    Cpu { limit_sec : u64 },
+    OpenFiles { limit : u64 },
+}
+
+//
+// Everything the runner needs from an execution in the child, sent back over a pipe
+//
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChildResult {
+    pub raw_result : i32,
+    pub func_error : Option<TestFuncError>,
+    pub undefined_params : Vec<String>,
+    pub resource_usage : Option<ResourceUsage>,
+    pub bench_samples : Vec<Duration>,
+    pub check_issues : Vec<CheckIssue>,
+    pub vm_peak_kb : i64,
+    pub open_files : u64,
+}
+
+//
+// What the child inherited from the runner at fork, taken before anything from the test is executed
+//
+#[derive(Debug, Clone, Copy, Default)]
+struct ChildBaseline {
+    vm_size_kb : i64,
+    open_files : u64,
+}
+
+impl ChildBaseline {
+    fn take() -> ChildBaseline {
+        Self {
+            vm_size_kb : fs::read("/proc/self/status").map(|data| parse_status_kb(&data, b"VmSize:")).unwrap_or_default(),
+            open_files : count_open_files(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ChildOutcome {
+    Completed(Box<ChildResult>),
+    Crashed { signal : c_int, vm_peak_kb : i64, frames : Vec<usize> },
+    Signaled { signal : c_int },
+    Exited { status : c_int },
+}
+
+impl ResourceLimits {
+    pub fn from_config() -> ResourceLimits {
+        let cfg = Config::instance();
+        Self {
+            address_space_mb : cfg.limit_as,
+            cpu_sec : cfg.limit_cpu,
+            open_files : cfg.limit_nofile,
+            core_mb : cfg.limit_core,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.address_space_mb.is_none() && self.cpu_sec.is_none() && self.open_files.is_none() && self.core_mb.is_none()
+    }
+
+    // Called in the child before executing anything from the test
+    fn apply(&self, baseline : &ChildBaseline) -> io::Result<()> {
+        if let Some(mb) = self.address_space_mb {
+            let bytes = mb * 1024 * 1024 + baseline.vm_size_kb.max(0) as u64 * 1024;
+            Self::set_limit(libc::RLIMIT_AS as c_int, bytes, bytes)?;
+        }
+        // The soft limit raises SIGXCPU, which is how we detect the violation - the hard limit is a safety net
+        if let Some(sec) = self.cpu_sec {
+            Self::set_limit(libc::RLIMIT_CPU as c_int, sec, sec + 1)?;
+        }
+        if let Some(n) = self.open_files {
+            // The limit is on the descriptor numbers, the inherited ones are the lowest
+            Self::set_limit(libc::RLIMIT_NOFILE as c_int, n + baseline.open_files, n + baseline.open_files)?;
+        }
+        if let Some(mb) = self.core_mb {
+            Self::set_limit(libc::RLIMIT_CORE as c_int, mb * 1024 * 1024, mb * 1024 * 1024)?;
+        }
+        Ok(())
+    }
+
+    // note: the resource type differs between libcs (unsigned on glibc), hence the plain int and the cast
+    fn set_limit(resource : c_int, soft : u64, hard : u64) -> io::Result<()> {
+        let limit = libc::rlimit { rlim_cur : soft, rlim_max : hard };
+        if unsafe { libc::setrlimit(resource as _, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Figure out if an abnormal or failed execution was caused by one of the limits
+    // A passing case is never a violation, whatever its usage was.
+    pub fn violation(&self, outcome : &ChildOutcome, child_usage : &libc::rusage) -> Option<LimitViolation> {
+        match outcome {
+            ChildOutcome::Crashed { signal, .. } | ChildOutcome::Signaled { signal } if self.cpu_sec.is_some() => {
+                let limit_sec = self.cpu_sec.unwrap_or_default();
+                let cpu_sec = (child_usage.ru_utime.tv_sec + child_usage.ru_stime.tv_sec) as u64;
+                if *signal == libc::SIGXCPU || (*signal == libc::SIGKILL && cpu_sec >= limit_sec) {
+                    return Some(LimitViolation::Cpu { limit_sec });
+                }
+            },
+            _ => (),
+        }
+
+        let (vm_peak_kb, open_files) = match outcome {
+            ChildOutcome::Completed(result) if result.did_fail() => (result.vm_peak_kb, result.open_files),
+            ChildOutcome::Completed(_) => return None,
+            ChildOutcome::Crashed { vm_peak_kb, .. } => (*vm_peak_kb, 0),
+            _ => return None,
+        };
+        if let Some(limit_mb) = self.address_space_mb {
+            let peak_mb = (vm_peak_kb / 1024) as u64;
+            if peak_mb as f64 >= limit_mb as f64 * AS_LIMIT_MARGIN {
+                return Some(LimitViolation::AddressSpace { limit_mb, peak_mb });
+            }
+        }
+        if let Some(limit) = self.open_files {
+            // At the limit means the test ran out
+            if open_files >= limit {
+                return Some(LimitViolation::OpenFiles { limit });
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitViolation::AddressSpace { limit_mb, peak_mb } => write!(f, "address space, peak {} MB (limit {} MB)", peak_mb, limit_mb),
+            LimitViolation::Cpu { limit_sec } => write!(f, "cpu time (limit {} sec)", limit_sec),
+            LimitViolation::OpenFiles { limit } => write!(f, "open files (limit {})", limit),
+        }
+    }
+}
+
+impl ChildResult {
+    // Snapshot of the process state, taken in the child once the test has finished
+    // Both are relative to what the child inherited, i.e. only the usage of the test.
+    fn with_process_state(mut self, baseline : &ChildBaseline) -> ChildResult {
+        let vm_peak_kb = fs::read("/proc/self/status").map(|data| parse_status_kb(&data, b"VmPeak:")).unwrap_or_default();
+        self.vm_peak_kb = (vm_peak_kb - baseline.vm_size_kb).max(0);
+        self.open_files = count_open_files().saturating_sub(baseline.open_files);
+        self
+    }
+
+    fn did_fail(&self) -> bool {
+        self.func_error.is_some() || self.raw_result != TestReturnCode::Pass as i32
+    }
+}
+
+// Includes the descriptor used for reading the directory, which cancels out against the baseline
+fn count_open_files() -> u64 {
+    match fs::read_dir("/proc/self/fd") {
+        Ok(dir) => dir.count() as u64,
+        // Not even a descriptor left for reading the directory
+        Err(err) if err.raw_os_error() == Some(libc::EMFILE) => u64::MAX,
+        Err(_) => 0,
+    }
+}
+
+// Parse a 'Key:   1234 kB' line from /proc/self/status
+// note: no allocations, this is used from the crash handler
+fn parse_status_kb(data : &[u8], key : &[u8]) -> i64 {
+    let Some(pos) = data.windows(key.len()).position(|w| w == key) else {
+        return 0;
+    };
+    let mut value = 0i64;
+    for &c in data[pos + key.len()..].iter().skip_while(|c| c.is_ascii_whitespace()) {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        value = value * 10 + (c - b'0') as i64;
+    }
+    value
+}
+
+// Append the decimal representation of 'value' to 'buf' at 'len', returns the new length
+fn format_decimal(buf : &mut [u8], mut len : usize, value : i64) -> usize {
+    let mut digits = [0u8; 20];
+    let mut n = 0;
+    let mut v = value.unsigned_abs();
+    loop {
+        digits[n] = b'0' + (v % 10) as u8;
+        n += 1;
+        v /= 10;
+        if v == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        buf[len] = b'-';
+        len += 1;
+    }
+    for i in (0..n).rev() {
+        buf[len] = digits[i];
+        len += 1;
+    }
+    len
+}
+
+// Installed in the child, reports the signal, the peak memory use and the backtrace before letting the signal terminate the child
+// note: only async-signal-safe calls in here (backtrace is prepared up front)
+extern "C" fn child_crash_handler(signal : c_int) {
+    let mut status = [0u8; 4096];
+    let mut vm_peak_kb = 0;
+    unsafe {
+        let fd = libc::open(c"/proc/self/status".as_ptr(), libc::O_RDONLY);
+        if fd >= 0 {
+            let len = libc::read(fd, status.as_mut_ptr() as *mut libc::c_void, status.len());
+            if len > 0 {
+                vm_peak_kb = (parse_status_kb(&status[..len as usize], b"VmPeak:") - VM_BASE_KB.load(Ordering::Relaxed)).max(0);
+            }
+            libc::close(fd);
+        }
+    }
+
+    let mut frames = [0usize; MAX_BACKTRACE_FRAMES];
+    let num_frames = capture_frames(&mut frames);
+
+    let mut msg = [0u8; 64 + MAX_BACKTRACE_FRAMES * 21];
+    let mut len = CRASH_TAG.len();
+    msg[..len].copy_from_slice(CRASH_TAG);
+    len = format_decimal(&mut msg, len, signal as i64);
+    msg[len] = b' ';
+    len = format_decimal(&mut msg, len + 1, vm_peak_kb);
+    for frame in &frames[..num_frames] {
+        msg[len] = b' ';
+        len = format_decimal(&mut msg, len + 1, *frame as i64);
+    }
+
+    unsafe {
+        libc::write(RESULT_FD.load(Ordering::Relaxed), msg.as_ptr() as *const libc::c_void, len);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+fn install_child_crash_handlers() {
+    prepare_backtrace();
+    for signal in [libc::SIGSEGV, libc::SIGBUS, libc::SIGFPE, libc::SIGILL, libc::SIGABRT, libc::SIGXCPU] {
+        unsafe {
+            libc::signal(signal, child_crash_handler as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+//
+// Execute 'func' in a forked child with the limits applied, the returned usage is the one reported for the child process
+//
+// The runner must be single threaded when calling this (the test thread has been joined), as only the calling thread exists in the child.
+//
+pub fn run_isolated<F : FnOnce() -> ChildResult>(limits : &ResourceLimits, func : F) -> io::Result<(ChildOutcome, libc::rusage)> {
+    fork_child(limits, None, func)
+}
+
+// Same as 'run_isolated' but the stderr output of the child is captured and returned, see death tests
+pub fn run_isolated_capture_stderr<F : FnOnce() -> ChildResult>(limits : &ResourceLimits, func : F) -> io::Result<(ChildOutcome, libc::rusage, String)> {
+    // An anonymous file instead of a pipe, nothing needs to read it while the child is running
+    let stderr_fd = unsafe { libc::memfd_create(c"tstrust-stderr".as_ptr(), libc::MFD_CLOEXEC) };
+    if stderr_fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut stderr_file = unsafe { File::from_raw_fd(stderr_fd) };
+    let (outcome, usage) = fork_child(limits, Some(stderr_fd), func)?;
+
+    let mut stderr = String::new();
+    stderr_file.seek(SeekFrom::Start(0))?;
+    stderr_file.read_to_string(&mut stderr).ok();
+    Ok((outcome, usage, stderr))
+}
+
+fn fork_child<F : FnOnce() -> ChildResult>(limits : &ResourceLimits, stderr_fd : Option<c_int>, func : F) -> io::Result<(ChildOutcome, libc::rusage)> {
+    let mut fds = [0 as c_int; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    // Anything buffered would otherwise be printed twice
+    io::stdout().flush().ok();
+
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        let err = io::Error::last_os_error();
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+        return Err(err);
+    }
+
+    if pid == 0 {
+        // Child, never returns
+        unsafe { libc::close(read_fd) };
+        if let Some(fd) = stderr_fd {
+            unsafe { libc::dup2(fd, libc::STDERR_FILENO) };
+        }
+        RESULT_FD.store(write_fd, Ordering::Relaxed);
+        let baseline = ChildBaseline::take();
+        VM_BASE_KB.store(baseline.vm_size_kb, Ordering::Relaxed);
+        install_child_crash_handlers();
+        if let Err(err) = limits.apply(&baseline) {
+            println!("ERR: Unable to apply resource limits: {}", err);
+        }
+
+        let result = func().with_process_state(&baseline);
+
+        let mut pipe = unsafe { File::from_raw_fd(write_fd) };
+        if let Ok(data) = serde_json::to_vec(&result) {
+            pipe.write_all(&data).ok();
+        }
+        // '_exit' skips all at-exit handling, flush both our and the C stdio buffers
+        io::stdout().flush().ok();
+        set_exit_interception(false);
+        unsafe {
+            libc::fflush(std::ptr::null_mut());
+            libc::_exit(0);
+        }
+    }
+
+    // Parent, read until the child closes the pipe (i.e. terminates) and reap it
+    unsafe { libc::close(write_fd) };
+    let mut data = Vec::new();
+    let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+    pipe.read_to_end(&mut data).ok();
+
+    let mut status : c_int = 0;
+    let mut usage : libc::rusage = unsafe { mem::zeroed() };
+    if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let outcome = if data.starts_with(CRASH_TAG) {
+        let text = String::from_utf8_lossy(&data[CRASH_TAG.len()..]).to_string();
+        let mut parts = text.split_whitespace().map(|p| p.parse::<i64>().unwrap_or_default());
+        let signal = parts.next().unwrap_or_default() as c_int;
+        let vm_peak_kb = parts.next().unwrap_or_default();
+        // The child is a fork, so the addresses are valid for symbolizing in the runner as well
+        let frames : Vec<usize> = parts.map(|frame| frame as usize).collect();
+        ChildOutcome::Crashed { signal, vm_peak_kb, frames : test_frames(&frames, true).to_vec() }
+    } else if let Ok(result) = serde_json::from_slice::<ChildResult>(&data) {
+        ChildOutcome::Completed(Box::new(result))
+    } else if libc::WIFSIGNALED(status) {
+        ChildOutcome::Signaled { signal : libc::WTERMSIG(status) }
+    } else {
+        ChildOutcome::Exited { status : libc::WEXITSTATUS(status) }
+    };
+    Ok((outcome, usage))
+}
+
+// Readable signal name for reports
+pub fn signal_name(signal : c_int) -> String {
+    let name = unsafe { libc::strsignal(signal) };
+    if name.is_null() {
+        return format!("signal {}", signal);
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy();
+    format!("signal {} ({})", signal, name)
+}
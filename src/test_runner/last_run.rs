@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+//
+// State of the last run, one line per executed case:
+//  <library>\t<module>\t<case>\t<symbol>\t<outcome>
+//
+// Used by '--rerun-failed' to select the cases which failed last time.
+// Library paths are stored canonicalized so the same library matches regardless of how it was specified.
+//
+#[derive(Debug, Clone)]
+pub struct LastRunEntry {
+    pub library : String,
+    pub module : String,
+    pub case : String,
+    pub symbol : String,
+    pub outcome : String,
+}
+
+#[derive(Debug, Default)]
+pub struct LastRun {
+    pub entries : Vec<LastRunEntry>,
+}
+
+impl LastRunEntry {
+    pub fn new(library : &str, module : &str, case : &str, symbol : &str, outcome : &str) -> LastRunEntry {
+        Self {
+            library : canonical_library_name(library),
+            module : module.to_string(),
+            case : case.to_string(),
+            symbol : symbol.to_string(),
+            outcome : outcome.to_string(),
+        }
+    }
+
+    pub fn did_fail(&self) -> bool {
+        self.outcome == "fail"
+    }
+}
+
+pub fn canonical_library_name(library : &str) -> String {
+    match fs::canonicalize(library) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => library.to_string(),
+    }
+}
+
+impl LastRun {
+    pub fn new() -> LastRun {
+        Self::default()
+    }
+
+    // Add an entry, a case executed several times (see --repeat) is kept once and counts as failed if any execution failed
+    pub fn add(&mut self, entry : LastRunEntry) {
+        let existing = self.entries.iter_mut().find(|e| e.library == entry.library && e.symbol == entry.symbol);
+        match existing {
+            Some(e) if entry.did_fail() => e.outcome = entry.outcome,
+            Some(_) => (),
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn load(filename : &Path) -> io::Result<LastRun> {
+        let data = fs::read_to_string(filename)?;
+        let mut last_run = LastRun::new();
+        for line in data.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts : Vec<&str> = line.split('\t').collect();
+            if parts.len() != 5 {
+                println!("WARN: Ignoring invalid line in '{}': {}", filename.display(), line);
+                continue;
+            }
+            last_run.entries.push(LastRunEntry {
+                library : parts[0].to_string(),
+                module : parts[1].to_string(),
+                case : parts[2].to_string(),
+                symbol : parts[3].to_string(),
+                outcome : parts[4].to_string(),
+            });
+        }
+        Ok(last_run)
+    }
+
+    pub fn save(&self, filename : &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "# tstrust last run: library, module, case, symbol, outcome")?;
+        for e in &self.entries {
+            writeln!(writer, "{}\t{}\t{}\t{}\t{}", e.library, e.module, e.case, e.symbol, e.outcome)?;
+        }
+        writer.flush()
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.entries.iter().any(LastRunEntry::did_fail)
+    }
+
+    pub fn is_failed_library(&self, library : &str) -> bool {
+        let library = canonical_library_name(library);
+        self.entries.iter().any(|e| e.did_fail() && e.library == library)
+    }
+
+    pub fn is_failed_module(&self, library : &str, module : &str) -> bool {
+        let library = canonical_library_name(library);
+        self.entries.iter().any(|e| e.did_fail() && e.library == library && e.module == module)
+    }
+
+    // note: matches on the case name, all instances of a parameterized case are executed if one of them failed
+    pub fn is_failed_case(&self, library : &str, module : &str, case : &str) -> bool {
+        let library = canonical_library_name(library);
+        self.entries.iter().any(|e| e.did_fail() && e.library == library && e.module == module && e.case == case)
+    }
+}
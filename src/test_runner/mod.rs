@@ -12,11 +12,36 @@ mod assert_error;
 mod context;
 
 mod config;
+mod config_file;
+mod params;
 mod singleton;
 mod test_runner;
 mod test_result;
 mod results_summary;
+mod case_stats;
+mod last_run;
+mod history;
+mod regression_report;
+mod bench;
+mod resource_usage;
+mod isolation;
+mod check_issue;
+mod fd_leaks;
+mod thread_leaks;
+#[cfg(feature = "heap-leak-check")]
+mod heap_leaks;
+mod global_state;
+mod crash_guard;
+mod native_backtrace;
+mod death_test;
+mod run_journal;
+mod run_control;
+mod sharding;
+mod schedule;
+mod exit_intercept;
 mod pthread;
+mod temp_dir;
+mod data_dir;
 
 // Now expose classes - this more or less will name-alias the classes into the test_runner namespace
 pub use dir_scanner::*;
@@ -27,9 +52,34 @@ pub use test_function::*;
 pub use assert_error::*;
 pub use context::*;
 pub use config::*;
+pub use config_file::*;
+pub use params::*;
 pub use singleton::*;
 pub use test_runner::*;
 pub use test_result::*;
 pub use results_summary::*;
+pub use case_stats::*;
+pub use last_run::*;
+pub use history::*;
+pub use regression_report::*;
+pub use bench::*;
+pub use resource_usage::*;
+pub use isolation::*;
+pub use check_issue::*;
+pub use fd_leaks::*;
+pub use thread_leaks::*;
+#[cfg(feature = "heap-leak-check")]
+pub use heap_leaks::*;
+pub use global_state::*;
+pub use crash_guard::*;
+pub use native_backtrace::*;
+pub use death_test::*;
+pub use run_journal::*;
+pub use run_control::*;
+pub use sharding::*;
+pub use schedule::*;
+pub use exit_intercept::*;
 pub use pthread::*;
+pub use temp_dir::*;
+pub use data_dir::*;
 
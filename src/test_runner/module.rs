@@ -19,6 +19,8 @@ pub struct Module {
     pub exit_func : Option<TestFunctionRef>,
     // regular test cases
     pub test_cases : Vec<TestFunctionRef>,
+    // Cleared when the module is filtered out by the selection (e.g. --rerun-failed)
+    pub selected : bool,
 }
 
 impl Module {
@@ -30,6 +32,7 @@ impl Module {
             post_case_func : None,
             pre_case_func : None,
             test_cases : Vec::new(),
+            selected : true,
         };
 
         return module;
@@ -38,6 +41,9 @@ impl Module {
     // Checks if we should execute
     // FIXME: Could also check 'flags' - this needs better impl - support for '!module' and '-m a*' etc..
     pub fn should_execute(&self) -> bool {
+        if !self.selected {
+            return false;
+        }
         let cfg = Config::instance();
         if cfg.modules.contains(&"-".to_string()) || cfg.modules.contains(&self.name) {
             return true;
@@ -45,16 +51,29 @@ impl Module {
         return false;
     }
 
+    // Reset all functions so the module can be executed again, parameterized cases stay expanded
+    pub fn reset(&mut self) {
+        self.pre_case_func = None;
+        self.post_case_func = None;
+
+        for func in self.main_func.iter().chain(self.exit_func.iter()).chain(self.test_cases.iter()) {
+            func.borrow_mut().reset();
+        }
+    }
+
     // Execute all functions in a module (incl. main/exit)
     pub fn execute(&mut self, dynlib : &DynLibraryRef) {
         // Execute main first, main can define various dependens plus pre/post functions
         self.execute_main(dynlib);
 
-        // Execute actual test cases
+        // Execute actual test cases, the exit is still executed when the run is stopped
         for tc in &self.test_cases {
             if !tc.borrow().should_execute() {
                 continue;
             }
+            if stop_before_start().is_some() {
+                break;
+            }
             self.execute_test(tc,dynlib);
         }
 
@@ -76,6 +95,18 @@ impl Module {
         self.pre_case_func = ctx.pre_case_handler;
         self.post_case_func = ctx.post_case_handler;
 
+        // expand parameterized cases, must be done before dependencies so they resolve to all instances
+        for case_params in &ctx.case_params {
+            self.expand_test_case(case_params);
+        }
+
+        // death tests, declared after the expansion so all parameterized instances are covered
+        for death in &ctx.death_expectations {
+            for tc in self.get_test_cases(death.case.as_str()) {
+                tc.borrow_mut().death = Some(death.expectation.clone());
+            }
+        }
+
         // handle dependencies
         if ctx.dependencies.is_empty() {
             return;
@@ -84,11 +115,11 @@ impl Module {
         // move dependencies over to their correct test-case
         // note: the dependency list contains a case and a list of dependencies for that case..
         //       thus we must have two loops..  one for the 'cases' and one for the dependencies..
-        //       plus this also checks if they are valid (if let...)
+        //       plus this also checks if they are valid (a case can resolve to several parameterized instances)
         for casedep in &ctx.dependencies {
-            if let Some(tc) = self.get_test_case(casedep.case.as_str()).ok() {
+            for tc in self.get_test_cases(casedep.case.as_str()) {
                 for dep in &casedep.dependencies {
-                    if let Some(tc_dep) = self.get_test_case(dep).ok() {
+                    for tc_dep in self.get_test_cases(dep) {
                         tc.borrow_mut().dependencies.push(tc_dep.clone());
                     }
                 }
@@ -96,6 +127,27 @@ impl Module {
         }
     }
 
+    // Replace a case with one instance per parameter set, keeping the position in the execution order
+    fn expand_test_case(&mut self, case_params : &CaseParams) {
+        // Only expand the plain case, this makes it a no-op if main is executed again
+        let idx = self.test_cases.iter().position(|tc| {
+            let tc = tc.borrow();
+            tc.case_name == case_params.case && tc.param.is_none()
+        });
+        let Some(idx) = idx else {
+            return;
+        };
+        // Without any parameter set the case would silently disappear, keep it as a plain case
+        if case_params.params.is_empty() {
+            println!("WARN: No parameters for case '{}' in module '{}', executing it without parameters", case_params.case, self.name);
+            return;
+        }
+
+        let tc = self.test_cases.remove(idx);
+        let instances: Vec<TestFunctionRef> = case_params.params.iter().map(|p| tc.borrow().with_param(p)).collect();
+        self.test_cases.splice(idx..idx, instances);
+    }
+
     // Execute the module exit, test_<module>_exit
     fn execute_exit(&mut self, dynlib : &DynLibraryRef) {
         if !self.exit_func.is_some() {
@@ -122,13 +174,12 @@ impl Module {
     }
 
 
-    fn get_test_case(&self, case : &str) -> Result<&TestFunctionRef, ()> {
-        for tc in &self.test_cases {
-            if tc.borrow().case_name == case {
-                return Ok(tc);
-            }
-        }
-        return Err(());
+    // Returns all cases matching a name, either the plain case name or a specific instance 'case[param]'
+    fn get_test_cases(&self, case : &str) -> Vec<&TestFunctionRef> {
+        self.test_cases
+            .iter()
+            .filter(|tc| tc.borrow().case_name == case || tc.borrow().display_name() == case)
+            .collect()
     }
 
     pub fn gather_test_results(&self) -> Vec<TestResult> {
@@ -148,8 +199,11 @@ impl Module {
             _ => (),
         }
 
+        // Cases not selected for execution have no result
         for tc in &self.test_cases {
-            test_results.push(tc.borrow().test_result.clone());
+            if tc.borrow().is_finished() {
+                test_results.push(tc.borrow().test_result.clone());
+            }
         }
 
         return test_results;
@@ -0,0 +1,95 @@
+use std::ffi::{c_int, c_void, CStr};
+use std::mem;
+
+//
+// Native backtraces of the test code, for failures and crashes (see TestFuncError) and leaked heap blocks
+// Frames are captured as raw addresses (cheap, usable from signal handlers) and symbolized afterwards with the
+// symbols and DWARF debug info of the loaded images.
+//
+
+pub const MAX_BACKTRACE_FRAMES : usize = 64;
+
+extern "C" {
+    fn backtrace(buffer : *mut *mut c_void, size : c_int) -> c_int;
+}
+
+// Raw return addresses of the calling thread, returns the number of frames stored
+// note: the first call loads the unwinder, call 'prepare_backtrace' before capturing from a signal handler
+pub fn capture_frames(frames : &mut [usize]) -> usize {
+    let num_frames = unsafe { backtrace(frames.as_mut_ptr() as *mut *mut c_void, frames.len() as c_int) };
+    num_frames.max(0) as usize
+}
+
+pub fn prepare_backtrace() {
+    let mut frames = [0usize; 1];
+    capture_frames(&mut frames);
+}
+
+// Capture and keep only the frames of the test code
+pub fn capture_test_frames() -> Vec<usize> {
+    let mut frames = [0usize; MAX_BACKTRACE_FRAMES];
+    let num_frames = capture_frames(&mut frames);
+    test_frames(&frames[..num_frames], false).to_vec()
+}
+
+// Drops the frames of the runner it-self, i.e. the capturing handler at the top and the thread entry at the bottom
+// When captured in a signal handler the signal trampoline following the handler is dropped as well.
+pub fn test_frames(frames : &[usize], in_signal_handler : bool) -> &[usize] {
+    let runner_base = image_base(capture_frames as *const () as usize);
+    let mut first = frames.iter().position(|frame| image_base(*frame) != runner_base).unwrap_or(frames.len());
+    if in_signal_handler && first < frames.len() {
+        first += 1;
+    }
+    let frames = &frames[first..];
+    let last = frames.iter().position(|frame| image_base(*frame) == runner_base).unwrap_or(frames.len());
+    &frames[..last]
+}
+
+// One line per frame; '#0 function at file:line (image)'
+pub fn symbolize(frames : &[usize]) -> Vec<String> {
+    frames.iter()
+        .enumerate()
+        .map(|(index, frame)| format!("#{} {}", index, describe_frame(*frame)))
+        .collect()
+}
+
+fn describe_frame(addr : usize) -> String {
+    let mut name = None;
+    let mut location = None;
+    // Return addresses point after the call, resolve the call instruction it-self
+    backtrace::resolve(addr.saturating_sub(1) as *mut c_void, |symbol| {
+        if name.is_none() {
+            name = symbol.name().map(|name| name.to_string());
+        }
+        if location.is_none() {
+            if let (Some(file), Some(line)) = (symbol.filename(), symbol.lineno()) {
+                location = Some(format!("{}:{}", file.display(), line));
+            }
+        }
+    });
+
+    let mut desc = name.unwrap_or_else(|| format!("{:#x}", addr));
+    if let Some(location) = location {
+        desc += &format!(" at {}", location);
+    }
+    if let Some(image) = image_name(addr) {
+        desc += &format!(" ({})", image);
+    }
+    desc
+}
+
+fn image_base(addr : usize) -> usize {
+    let mut info : libc::Dl_info = unsafe { mem::zeroed() };
+    match unsafe { libc::dladdr(addr as *const c_void, &mut info) } {
+        0 => 0,
+        _ => info.dli_fbase as usize,
+    }
+}
+
+fn image_name(addr : usize) -> Option<String> {
+    let mut info : libc::Dl_info = unsafe { mem::zeroed() };
+    if unsafe { libc::dladdr(addr as *const c_void, &mut info) } == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(info.dli_fname) }.to_string_lossy().to_string())
+}
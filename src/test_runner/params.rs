@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use once_cell::sync::Lazy;
+use crate::test_runner::{Config, Singleton, CONFIG_FILE};
+
+//
+// Runner provided key/value parameters, available to tests through 'get_param'
+// Values are taken from the '[params]' section of the config file, '-D key=value' overrides them.
+//
+#[derive(Default)]
+pub struct Params {
+    values : HashMap<String, CString>,
+}
+
+pub static PARAMS: Lazy<Params> = Lazy::new(|| {
+    let mut params = Params::new();
+    for (key, value) in CONFIG_FILE.section("params") {
+        params.set(key, value);
+    }
+    for define in &Config::instance().defines {
+        match define.split_once('=') {
+            Some((key, value)) => params.set(key.trim(), value.trim()),
+            None => params.set(define.trim(), ""),
+        }
+    }
+    params
+});
+
+impl Params {
+    pub fn new() -> Params {
+        Self::default()
+    }
+
+    pub fn set(&mut self, key : &str, value : &str) {
+        match CString::new(value) {
+            Ok(str_value) => {
+                self.values.insert(key.to_string(), str_value);
+            },
+            Err(_) => println!("WARN: Ignoring parameter '{}', value contains a null character", key),
+        }
+    }
+
+    // note: the values are never modified after creation, so the returned strings are valid for the whole run
+    pub fn get(&self, key : &str) -> Option<&CStr> {
+        self.values.get(key).map(CString::as_c_str)
+    }
+}
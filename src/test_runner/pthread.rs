@@ -1,21 +1,61 @@
+use std::collections::HashSet;
 use std::ffi::c_void;
+use std::sync::Mutex;
 use std::{mem, ptr};
-use libc::{pthread_attr_init, pthread_attr_t, pthread_create, pthread_join, pthread_t};
+use libc::{pid_t, pthread_attr_init, pthread_attr_t, pthread_create, pthread_join, pthread_t};
+use once_cell::sync::Lazy;
 
 pub struct PThread<T> {
     thread_arg : T,
+    func : Option<PThreadFunc>,
     h_thread : pthread_t,
 }
-pub type PThreadFunc = extern "C" fn(*mut c_void) -> *mut c_void;
+// 'C-unwind' as the thread can be terminated with 'pthread_exit' (which unwinds) from within the test
+pub type PThreadFunc = extern "C-unwind" fn(*mut c_void) -> *mut c_void;
+
+// The libc binding is 'C', which doesn't allow the forced unwind to pass the call site
+extern "C-unwind" {
+    pub fn pthread_exit(retval : *mut c_void) -> !;
+}
+
+// Kernel thread id's of the threads currently running through PThread, see --check-thread-leaks
+static RUNNER_THREADS: Lazy<Mutex<HashSet<pid_t>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn is_runner_thread(tid : pid_t) -> bool {
+    RUNNER_THREADS.lock().unwrap().contains(&tid)
+}
+
+// Removes the thread from the registry, also when leaving through 'pthread_exit'
+struct RunnerThreadGuard {
+    tid : pid_t,
+}
+
+impl Drop for RunnerThreadGuard {
+    fn drop(&mut self) {
+        RUNNER_THREADS.lock().unwrap().remove(&self.tid);
+    }
+}
+
+extern "C-unwind" fn pthread_trampoline<T>(ptr_self : *mut c_void) -> *mut c_void {
+    let tid = unsafe { libc::gettid() };
+    RUNNER_THREADS.lock().unwrap().insert(tid);
+    let _guard = RunnerThreadGuard { tid };
+
+    let this = unsafe { &mut *(ptr_self as *mut PThread<T>) };
+    let ptr_arg : *mut c_void = &mut this.thread_arg as *mut _ as *mut c_void;
+    this.func.unwrap()(ptr_arg)
+}
 
 impl<T> PThread<T> {
     pub fn new(arg : T) -> PThread<T> {
         Self {
             thread_arg : arg,
+            func : None,
             h_thread : unsafe { mem::zeroed() },
         }
     }
 
+    // Note: the PThread instance must not move until the thread has been joined
     pub fn spawn(&mut self, func : PThreadFunc) -> Result<(),&str>{
         let mut attr : pthread_attr_t = unsafe { mem::zeroed() };
         //let mut h_thread : pthread_t = unsafe { mem::zeroed() };
@@ -23,12 +63,16 @@ impl<T> PThread<T> {
         let attr_ptr : *mut pthread_attr_t = &mut attr;
         let h_thread_ptr : *mut pthread_t = &mut self.h_thread;
 
-        let ptr_arg : *mut c_void = &mut self.thread_arg as *mut _ as *mut c_void;
+        self.func = Some(func);
+        let ptr_self : *mut c_void = self as *mut _ as *mut c_void;
 
         unsafe {
             pthread_attr_init(attr_ptr);
 
-            let err = pthread_create(h_thread_ptr, attr_ptr, func, ptr_arg);
+            // Only the unwind behaviour differs, the calling convention is the same
+            let trampoline : PThreadFunc = pthread_trampoline::<T>;
+            let start_routine : extern "C" fn(*mut c_void) -> *mut c_void = mem::transmute(trampoline);
+            let err = pthread_create(h_thread_ptr, attr_ptr, start_routine, ptr_self);
             if err != 0 {
                 return Err("pthread create failed!");
             }
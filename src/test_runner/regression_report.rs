@@ -0,0 +1,146 @@
+use std::collections::{HashMap, HashSet};
+use crate::test_runner::{CaseRecord, RunRecord};
+
+// Durations below this are mostly noise, they are never reported as regressions
+const MIN_REGRESSION_DURATION_SEC : f64 = 0.001;
+
+//
+// Compares the current run with a previous one
+// A previous case is only removed if its module was executed in this run and the library no longer exports it, so
+// running a subset (-m/-t, --rerun-failed, sharding) doesn't list everything else as removed.
+//
+pub struct RegressionReport<'a> {
+    pub new_failures : Vec<&'a CaseRecord>,
+    pub fixed : Vec<&'a CaseRecord>,
+    pub added : Vec<&'a CaseRecord>,
+    pub removed : Vec<&'a CaseRecord>,
+    pub slower : Vec<(&'a CaseRecord, &'a CaseRecord)>,     // (previous, current)
+}
+
+impl<'a> RegressionReport<'a> {
+    // 'exports' holds the test functions exported per library (keyed as in the history), see 'TestRunner::exported_symbols'
+    pub fn new(previous : &'a RunRecord, current : &'a RunRecord, exports : &HashMap<String, HashSet<String>>, threshold_percent : f64) -> RegressionReport<'a> {
+        let mut report = RegressionReport {
+            new_failures : Vec::new(),
+            fixed : Vec::new(),
+            added : Vec::new(),
+            removed : Vec::new(),
+            slower : Vec::new(),
+        };
+
+        for case in &current.cases {
+            let Some(prev) = previous.find(case) else {
+                report.added.push(case);
+                continue;
+            };
+            if case.did_fail() && !prev.did_fail() {
+                report.new_failures.push(case);
+            } else if !case.did_fail() && prev.did_fail() {
+                report.fixed.push(case);
+            }
+            let limit = prev.duration_sec * (1.0 + threshold_percent / 100.0);
+            if case.duration_sec > limit && case.duration_sec >= MIN_REGRESSION_DURATION_SEC {
+                report.slower.push((prev, case));
+            }
+        }
+
+        for prev in &previous.cases {
+            let module_executed = current.cases.iter().any(|c| c.library == prev.library && c.module == prev.module);
+            // The symbol of a parameterized case includes the parameter set, 'test_module_case[param]'
+            let function = prev.symbol.split('[').next().unwrap_or_default();
+            let exported = exports.get(&prev.library).is_some_and(|symbols| symbols.contains(function));
+            if module_executed && !exported && current.find(prev).is_none() {
+                report.removed.push(prev);
+            }
+        }
+        report
+    }
+
+    pub fn print(&self) {
+        println!("Regression report:");
+        println!("New failures..: {}", self.new_failures.len());
+        for case in &self.new_failures {
+            println!("  {}", case.symbol);
+        }
+        println!("Fixed.........: {}", self.fixed.len());
+        for case in &self.fixed {
+            println!("  {}", case.symbol);
+        }
+        println!("Added.........: {}", self.added.len());
+        for case in &self.added {
+            println!("  {}", case.symbol);
+        }
+        println!("Removed.......: {}", self.removed.len());
+        for case in &self.removed {
+            println!("  {}", case.symbol);
+        }
+        println!("Slower........: {}", self.slower.len());
+        for (prev, case) in &self.slower {
+            // Too fast to be measured before, there is no meaningful increase
+            if prev.duration_sec <= 0.0 {
+                println!("  {}: {} sec -> {} sec", case.symbol, prev.duration_sec, case.duration_sec);
+                continue;
+            }
+            println!("  {}: {} sec -> {} sec (+{:.1}%)", case.symbol, prev.duration_sec, case.duration_sec,
+                     (case.duration_sec / prev.duration_sec - 1.0) * 100.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(cases : &[(&str, &str, &str, f64)]) -> RunRecord {
+        let mut run = RunRecord::new();
+        for (module, symbol, outcome, duration_sec) in cases {
+            run.add(CaseRecord::new("libA.so", module, "case", symbol, outcome, *duration_sec));
+        }
+        run
+    }
+
+    fn exports(symbols : &[&str]) -> HashMap<String, HashSet<String>> {
+        HashMap::from([("libA.so".to_string(), symbols.iter().map(|s| s.to_string()).collect())])
+    }
+
+    fn symbols(cases : &[&CaseRecord]) -> Vec<String> {
+        cases.iter().map(|case| case.symbol.clone()).collect()
+    }
+
+    #[test]
+    fn failures_fixed_and_added() {
+        let previous = run(&[("a", "test_a_x", "pass", 1.0), ("a", "test_a_y", "fail", 1.0)]);
+        let current = run(&[("a", "test_a_x", "fail", 1.0), ("a", "test_a_y", "pass", 1.0), ("a", "test_a_z", "pass", 1.0)]);
+        let report = RegressionReport::new(&previous, &current, &exports(&["test_a_x", "test_a_y", "test_a_z"]), 20.0);
+        assert_eq!(symbols(&report.new_failures), vec!["test_a_x"]);
+        assert_eq!(symbols(&report.fixed), vec!["test_a_y"]);
+        assert_eq!(symbols(&report.added), vec!["test_a_z"]);
+        assert!(report.removed.is_empty());
+    }
+
+    #[test]
+    fn removed_only_when_no_longer_exported() {
+        let previous = run(&[("a", "test_a_x", "pass", 1.0), ("a", "test_a_y", "pass", 1.0), ("a", "test_a_z[1]", "pass", 1.0)]);
+        let current = run(&[("a", "test_a_x", "pass", 1.0)]);
+        // 'y' was not selected this time, 'z' (parameterized) is gone from the library
+        let report = RegressionReport::new(&previous, &current, &exports(&["test_a_x", "test_a_y"]), 20.0);
+        assert_eq!(symbols(&report.removed), vec!["test_a_z[1]"]);
+    }
+
+    #[test]
+    fn not_removed_when_module_not_executed() {
+        let previous = run(&[("a", "test_a_x", "pass", 1.0), ("b", "test_b_x", "pass", 1.0)]);
+        let current = run(&[("a", "test_a_x", "pass", 1.0)]);
+        let report = RegressionReport::new(&previous, &current, &exports(&["test_a_x"]), 20.0);
+        assert!(report.removed.is_empty());
+    }
+
+    #[test]
+    fn slower_above_threshold() {
+        let previous = run(&[("a", "test_a_x", "pass", 1.0), ("a", "test_a_y", "pass", 1.0), ("a", "test_a_z", "pass", 0.0)]);
+        let current = run(&[("a", "test_a_x", "pass", 1.1), ("a", "test_a_y", "pass", 1.5), ("a", "test_a_z", "pass", 0.01)]);
+        let report = RegressionReport::new(&previous, &current, &exports(&["test_a_x", "test_a_y", "test_a_z"]), 20.0);
+        let slower : Vec<&str> = report.slower.iter().map(|(_, case)| case.symbol.as_str()).collect();
+        assert_eq!(slower, vec!["test_a_y", "test_a_z"]);
+    }
+}
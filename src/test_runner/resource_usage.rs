@@ -0,0 +1,67 @@
+use std::mem;
+use serde::{Deserialize, Serialize};
+
+//
+// Resource usage of a single test function execution
+// CPU times, faults and context switches are measured on the test thread (RUSAGE_THREAD),
+// peak RSS is only available for the process so it is the growth of the process peak during the test.
+//
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub user_time_sec : f64,
+    pub sys_time_sec : f64,
+    pub max_rss_delta_kb : i64,
+    pub minor_faults : i64,
+    pub major_faults : i64,
+    pub voluntary_ctx_switches : i64,
+    pub involuntary_ctx_switches : i64,
+}
+
+// Raw usage at a point in time, taken on the test thread
+#[derive(Clone, Copy)]
+pub struct UsageSnapshot {
+    thread : libc::rusage,
+    max_rss_kb : i64,
+}
+
+impl UsageSnapshot {
+    // Must be called on the thread being measured
+    pub fn take() -> UsageSnapshot {
+        let mut thread : libc::rusage = unsafe { mem::zeroed() };
+        let mut process : libc::rusage = unsafe { mem::zeroed() };
+        unsafe {
+            libc::getrusage(libc::RUSAGE_THREAD, &mut thread);
+            libc::getrusage(libc::RUSAGE_SELF, &mut process);
+        }
+        Self {
+            thread,
+            max_rss_kb : process.ru_maxrss,
+        }
+    }
+}
+
+fn timeval_sec(tv : &libc::timeval) -> f64 {
+    tv.tv_sec as f64 + tv.tv_usec as f64 / 1_000_000.0
+}
+
+impl ResourceUsage {
+    pub fn between(start : &UsageSnapshot, end : &UsageSnapshot) -> ResourceUsage {
+        let (s, e) = (&start.thread, &end.thread);
+        Self {
+            user_time_sec : timeval_sec(&e.ru_utime) - timeval_sec(&s.ru_utime),
+            sys_time_sec : timeval_sec(&e.ru_stime) - timeval_sec(&s.ru_stime),
+            max_rss_delta_kb : end.max_rss_kb - start.max_rss_kb,
+            minor_faults : e.ru_minflt - s.ru_minflt,
+            major_faults : e.ru_majflt - s.ru_majflt,
+            voluntary_ctx_switches : e.ru_nvcsw - s.ru_nvcsw,
+            involuntary_ctx_switches : e.ru_nivcsw - s.ru_nivcsw,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("  Usage: user {} sec, sys {} sec, max rss +{} kb, faults {} minor/{} major, ctx switches {} vol/{} invol",
+                 self.user_time_sec, self.sys_time_sec, self.max_rss_delta_kb,
+                 self.minor_faults, self.major_faults,
+                 self.voluntary_ctx_switches, self.involuntary_ctx_switches);
+    }
+}
@@ -6,6 +6,7 @@ pub struct ResultSummary {
     pub module_name : String,
     pub tests_executed : i32,
     pub tests_failed : i32,
+    pub tests_flaky : i32,
     pub duration_sec : f32,
     pub test_results : Vec<TestResult>,
 }
@@ -17,6 +18,7 @@ impl ResultSummary {
             module_name : module_name.to_string(),
             tests_executed : 0,
             tests_failed : 0,
+            tests_flaky : 0,
             duration_sec : 0f32,        // This should not be here???  [it is in the C/C++ version]
             test_results : Vec::new(),
         }
@@ -28,6 +30,7 @@ impl ResultSummary {
             module_name : module.name.clone(),
             tests_executed : 0,
             tests_failed : 0,
+            tests_flaky : 0,
             duration_sec : 0f32,        // This should not be here???  [it is in the C/C++ version]
             test_results : mod_results,
         };
@@ -44,6 +47,7 @@ impl ResultSummary {
         // reset
         self.tests_executed = 0;
         self.tests_failed = 0;
+        self.tests_flaky = 0;
 
         // re-count
         for r in &self.test_results {
@@ -51,6 +55,17 @@ impl ResultSummary {
             if r.did_fail() {
                 self.tests_failed += 1;
             }
+            if r.flaky {
+                self.tests_flaky += 1;
+            }
+        }
+    }
+
+    pub fn print_flaky(&self) {
+        for r in &self.test_results {
+            if r.flaky {
+                r.print_flaky();
+            }
         }
     }
 
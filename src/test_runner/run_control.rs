@@ -0,0 +1,136 @@
+use std::fmt;
+use std::mem;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use libc::c_int;
+use once_cell::sync::OnceCell;
+
+//
+// Stopping a run early
+// Once a stop has been requested no new cases, modules or libraries are started. The running case is allowed to
+// finish, pending module and library exit functions are still executed and the results are reported as usual.
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StopReason {
+    Interrupted = 1,
+    MaxFailures = 2,
+    TimeBudget = 3,
+}
+
+impl StopReason {
+    fn from_u8(value : u8) -> Option<StopReason> {
+        match value {
+            1 => Some(StopReason::Interrupted),
+            2 => Some(StopReason::MaxFailures),
+            3 => Some(StopReason::TimeBudget),
+            _ => None,
+        }
+    }
+
+    // Exit status of the runner when the run was stopped for this reason, None for a normal exit
+    pub fn exit_status(&self) -> Option<i32> {
+        match self {
+            // Same as a shell reports for a process killed by SIGINT
+            StopReason::Interrupted => Some(128 + libc::SIGINT),
+            StopReason::MaxFailures | StopReason::TimeBudget => None,
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Interrupted => write!(f, "interrupted"),
+            StopReason::MaxFailures => write!(f, "maximum number of failures reached"),
+            StopReason::TimeBudget => write!(f, "time budget used up"),
+        }
+    }
+}
+
+// 0 means keep going, set from the signal handler as well - must stay lock free
+static STOP_REASON : AtomicU8 = AtomicU8::new(0);
+
+// The first reason is kept, except for an interruption which always wins (it decides the exit status)
+pub fn request_stop(reason : StopReason) {
+    if reason == StopReason::Interrupted {
+        STOP_REASON.store(reason as u8, Ordering::SeqCst);
+        return;
+    }
+    let _ = STOP_REASON.compare_exchange(0, reason as u8, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+pub fn stop_requested() -> Option<StopReason> {
+    if DEADLINE.get().is_some_and(|deadline| Instant::now() >= *deadline) {
+        request_stop(StopReason::TimeBudget);
+    }
+    StopReason::from_u8(STOP_REASON.load(Ordering::SeqCst))
+}
+
+// The reason at the first point a stop actually kept something from being started, see 'stop_before_start'
+static STOPPED_REASON : AtomicU8 = AtomicU8::new(0);
+
+// Checked before starting an iteration, library, module or case that would otherwise be executed
+// Returns the reason if it must not be started, the run is then reported as stopped.
+pub fn stop_before_start() -> Option<StopReason> {
+    let reason = stop_requested()?;
+    let _ = STOPPED_REASON.compare_exchange(0, reason as u8, Ordering::SeqCst, Ordering::SeqCst);
+    Some(reason)
+}
+
+// Why the run was stopped early, None if nothing was skipped (e.g. the time budget ran out during the last case)
+// An interruption still wins over the reason latched before, it decides the exit status
+pub fn run_stopped() -> Option<StopReason> {
+    let reason = StopReason::from_u8(STOPPED_REASON.load(Ordering::SeqCst))?;
+    match stop_requested() {
+        Some(StopReason::Interrupted) => Some(StopReason::Interrupted),
+        _ => Some(reason),
+    }
+}
+
+// End of the --time-budget, checked whenever the run asks if it should stop
+static DEADLINE : OnceCell<Instant> = OnceCell::new();
+
+pub fn start_time_budget(budget : Duration) {
+    let _ = DEADLINE.set(Instant::now() + budget);
+}
+
+// Failing cases over the whole run, see --fail-fast and --max-failures
+static NUM_FAILURES : AtomicU32 = AtomicU32::new(0);
+
+// Called for every failing case, stops the run once 'limit' cases have failed
+pub fn record_failure(limit : Option<u32>) {
+    let num_failures = NUM_FAILURES.fetch_add(1, Ordering::SeqCst) + 1;
+    if limit.is_some_and(|limit| num_failures >= limit) {
+        request_stop(StopReason::MaxFailures);
+    }
+}
+
+static INTERRUPTED : AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt_handler(_signal : c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // Second Ctrl-C, give up right away
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::raise(libc::SIGINT);
+        }
+        return;
+    }
+    request_stop(StopReason::Interrupted);
+
+    // Only async-signal-safe calls in here
+    let msg = b"\nInterrupted, finishing the running case (Ctrl-C again to abort)\n";
+    unsafe { libc::write(libc::STDOUT_FILENO, msg.as_ptr() as *const libc::c_void, msg.len()) };
+}
+
+// First SIGINT stops the run, the second one aborts
+pub fn install_interrupt_handler() {
+    let mut action : libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = interrupt_handler as *const () as libc::sighandler_t;
+    // Restart waits (e.g. for an isolated child) instead of failing them with EINTR
+    action.sa_flags = libc::SA_RESTART;
+    if unsafe { libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) } != 0 {
+        println!("ERR: Unable to install SIGINT handler, Ctrl-C will abort the run");
+    }
+}
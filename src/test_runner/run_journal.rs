@@ -0,0 +1,280 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+use crate::test_runner::StopReason;
+
+//
+// Progress of the run, kept up to date as cases start and finish
+// Used to produce a partial report when the process is about to die in the middle of a run (e.g. 'exit' called by a test)
+//
+// With --journal the progress is also appended to a file, one tab separated line per event:
+//  RUN_START\t<unix time ms>\t<pid>
+//  CASE_START\t<library>\t<symbol>
+//  CASE_END\t<library>\t<symbol>\t<outcome>\t<duration sec>
+//  RUN_STOPPED\t<reason>                   (only when the run was stopped early, e.g. Ctrl-C)
+//  RUN_END\t<executed>\t<failed>
+//
+// Every line is written directly to the file, so the journal is complete up to the point where the process died.
+// See '--journal-report' for turning it into a (partial) report.
+//
+#[derive(Default)]
+pub struct RunJournal {
+    running_case : Option<String>,
+    num_executed : usize,
+    failed : Vec<String>,
+    file : Option<File>,
+}
+
+pub static RUN_JOURNAL: Lazy<Mutex<RunJournal>> = Lazy::new(|| Mutex::new(RunJournal::default()));
+
+impl RunJournal {
+    // Start appending to a journal file, previous runs in the file are kept
+    pub fn open_file(&mut self, filename : &Path) -> io::Result<()> {
+        self.file = Some(OpenOptions::new().create(true).append(true).open(filename)?);
+        Ok(())
+    }
+
+    pub fn run_started(&mut self) {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default();
+        self.append(&format!("RUN_START\t{}\t{}", timestamp_ms, std::process::id()));
+    }
+
+    pub fn run_stopped(&mut self, reason : StopReason) {
+        self.append(&format!("RUN_STOPPED\t{}", reason));
+    }
+
+    pub fn run_finished(&mut self, num_executed : i32, num_failed : i32) {
+        self.append(&format!("RUN_END\t{}\t{}", num_executed, num_failed));
+    }
+
+    pub fn case_started(&mut self, library : &str, symbol : &str) {
+        self.running_case = Some(symbol.to_string());
+        self.append(&format!("CASE_START\t{}\t{}", library, symbol));
+    }
+
+    pub fn case_finished(&mut self, library : &str, symbol : &str, outcome : &str, duration : Duration) {
+        self.running_case = None;
+        self.num_executed += 1;
+        if outcome == "fail" {
+            self.failed.push(symbol.to_string());
+        }
+        self.append(&format!("CASE_END\t{}\t{}\t{}\t{}", library, symbol, outcome, duration.as_secs_f64()));
+    }
+
+    pub fn running_case(&self) -> Option<&str> {
+        self.running_case.as_deref()
+    }
+
+    // The running case is reported as failed with 'reason', followed by what was executed up to now
+    pub fn print_partial_report(&self, reason : &str) {
+        let running_case = self.running_case.as_deref().unwrap_or("-");
+        println!("=== FAIL:\t{}, {}", running_case, reason);
+        println!("-------------------");
+        println!("Run aborted, partial results");
+        println!("Tests Executed: {}", self.num_executed + 1);
+        println!("Tests Failed..: {}", self.failed.len() + 1);
+        println!("Failed:");
+        for symbol in &self.failed {
+            println!("  [Tma]: {}", symbol);
+        }
+        println!("  [Tma]: {}, {}", running_case, reason);
+    }
+
+    fn append(&mut self, line : &str) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        if let Err(err) = file.write_all(format!("{}\n", line).as_bytes()) {
+            println!("ERR: Unable to write to journal: {}", err);
+            self.file = None;
+        }
+    }
+}
+
+//
+// The last run in a journal file, see '--journal-report'
+//
+#[derive(Debug, Default)]
+pub struct JournalReport {
+    pub timestamp_ms : u64,
+    pub pid : u32,
+    pub executed : Vec<JournalCase>,
+    // Started but never finished, i.e. the case executing when the process died
+    pub running : Option<JournalCase>,
+    pub completed : bool,
+    pub stop_reason : Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalCase {
+    pub library : String,
+    pub symbol : String,
+    pub outcome : String,
+    pub duration_sec : f64,
+}
+
+impl JournalReport {
+    pub fn load(filename : &Path) -> io::Result<JournalReport> {
+        Self::parse(&fs::read_to_string(filename)?)
+    }
+
+    pub fn parse(journal : &str) -> io::Result<JournalReport> {
+        let mut report = JournalReport::default();
+        let mut found_run = false;
+        for line in journal.lines() {
+            let fields : Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["RUN_START", timestamp_ms, pid] => {
+                    // Only the last run in the file is reported
+                    report = JournalReport {
+                        timestamp_ms : timestamp_ms.parse().unwrap_or_default(),
+                        pid : pid.parse().unwrap_or_default(),
+                        ..Default::default()
+                    };
+                    found_run = true;
+                },
+                ["CASE_START", library, symbol] => {
+                    report.running = Some(JournalCase {
+                        library : library.to_string(),
+                        symbol : symbol.to_string(),
+                        outcome : String::new(),
+                        duration_sec : 0.0,
+                    });
+                },
+                ["CASE_END", library, symbol, outcome, duration_sec] => {
+                    report.running = None;
+                    report.executed.push(JournalCase {
+                        library : library.to_string(),
+                        symbol : symbol.to_string(),
+                        outcome : outcome.to_string(),
+                        duration_sec : duration_sec.parse().unwrap_or_default(),
+                    });
+                },
+                ["RUN_STOPPED", reason] => report.stop_reason = Some(reason.to_string()),
+                ["RUN_END", ..] => report.completed = true,
+                // A line cut short when the process died
+                _ => (),
+            }
+        }
+        if !found_run {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no run in journal"));
+        }
+        Ok(report)
+    }
+
+    pub fn num_failed(&self) -> usize {
+        self.executed.iter().filter(|case| case.outcome == "fail").count()
+    }
+
+    pub fn print(&self) {
+        println!("Run started...: {} (unix time ms), pid {}", self.timestamp_ms, self.pid);
+        println!("Tests Executed: {}", self.executed.len());
+        println!("Tests Failed..: {}", self.num_failed());
+        if self.num_failed() > 0 {
+            println!("Failed:");
+            for case in self.executed.iter().filter(|case| case.outcome == "fail") {
+                println!("  [Tma]: {} ({})", case.symbol, case.library);
+            }
+        }
+
+        if self.completed {
+            match &self.stop_reason {
+                Some(reason) => println!("Run stopped: {}", reason),
+                None => println!("Run completed"),
+            }
+            return;
+        }
+        match &self.running {
+            Some(case) => {
+                println!("Run incomplete, the process died while executing:");
+                println!("  {} ({})", case.symbol, case.library);
+            },
+            None => println!("Run incomplete, the process died between test cases"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_run() {
+        assert!(JournalReport::parse("").is_err());
+        // Only a cut short run start
+        assert!(JournalReport::parse("RUN_ST").is_err());
+    }
+
+    #[test]
+    fn completed_run() {
+        let report = JournalReport::parse("RUN_START\t1000\t42\n\
+            CASE_START\tlibA.so\ttest_a_x\n\
+            CASE_END\tlibA.so\ttest_a_x\tpass\t0.5\n\
+            CASE_START\tlibA.so\ttest_a_y\n\
+            CASE_END\tlibA.so\ttest_a_y\tfail\t1.5\n\
+            RUN_END\t2\t1\n").unwrap();
+        assert_eq!(report.timestamp_ms, 1000);
+        assert_eq!(report.pid, 42);
+        assert_eq!(report.executed.len(), 2);
+        assert_eq!(report.executed[1].symbol, "test_a_y");
+        assert_eq!(report.executed[1].duration_sec, 1.5);
+        assert_eq!(report.num_failed(), 1);
+        assert!(report.running.is_none());
+        assert!(report.completed);
+        assert!(report.stop_reason.is_none());
+    }
+
+    #[test]
+    fn stopped_run() {
+        let report = JournalReport::parse("RUN_START\t1000\t42\n\
+            CASE_START\tlibA.so\ttest_a_x\n\
+            CASE_END\tlibA.so\ttest_a_x\tfail\t0.5\n\
+            RUN_STOPPED\tinterrupted\n\
+            RUN_END\t1\t1\n").unwrap();
+        assert!(report.completed);
+        assert_eq!(report.stop_reason.as_deref(), Some("interrupted"));
+    }
+
+    #[test]
+    fn died_in_case() {
+        let report = JournalReport::parse("RUN_START\t1000\t42\n\
+            CASE_START\tlibA.so\ttest_a_x\n\
+            CASE_END\tlibA.so\ttest_a_x\tpass\t0.5\n\
+            CASE_START\tlibA.so\ttest_a_y\n").unwrap();
+        assert!(!report.completed);
+        assert_eq!(report.executed.len(), 1);
+        assert_eq!(report.running.unwrap().symbol, "test_a_y");
+    }
+
+    #[test]
+    fn truncated_last_line() {
+        // The process died while the case end was written, the case is still the running one
+        let report = JournalReport::parse("RUN_START\t1000\t42\n\
+            CASE_START\tlibA.so\ttest_a_x\n\
+            CASE_END\tlibA.so\ttest_a").unwrap();
+        assert!(!report.completed);
+        assert!(report.executed.is_empty());
+        assert_eq!(report.running.unwrap().symbol, "test_a_x");
+    }
+
+    #[test]
+    fn last_run_is_reported() {
+        let report = JournalReport::parse("RUN_START\t1000\t42\n\
+            CASE_START\tlibA.so\ttest_a_x\n\
+            CASE_END\tlibA.so\ttest_a_x\tfail\t0.5\n\
+            RUN_STOPPED\tinterrupted\n\
+            RUN_END\t1\t1\n\
+            RUN_START\t2000\t43\n\
+            CASE_START\tlibA.so\ttest_a_y\n").unwrap();
+        assert_eq!(report.timestamp_ms, 2000);
+        assert_eq!(report.pid, 43);
+        assert!(report.executed.is_empty());
+        assert_eq!(report.num_failed(), 0);
+        assert!(!report.completed);
+        assert!(report.stop_reason.is_none());
+        assert_eq!(report.running.unwrap().symbol, "test_a_y");
+    }
+}
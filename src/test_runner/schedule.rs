@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use crate::test_runner::{History, RunRecord};
+
+//
+// Expected module durations from recorded runs, used to execute the longest modules first (local history) and to
+// balance shards (see --shard-timings).
+// Every case keeps its duration from the most recent run it was executed in, a module is expected to take the sum of
+// its cases (incl. main/exit). Modules without a recorded duration are expected to take the mean of the known modules.
+// Without any recorded run nothing is known and the current order is kept.
+// Libraries are keyed by their file name, timings recorded on another machine or checkout (e.g. in CI) still match.
+//
+#[derive(Debug, Default)]
+pub struct DurationEstimates {
+    modules : HashMap<(String, String), f64>,
+    mean_sec : f64,
+}
+
+impl DurationEstimates {
+    // From the local run history, errors are reported and leaves nothing known
+    pub fn from_history(history : &History) -> DurationEstimates {
+        match history.runs() {
+            Ok(runs) => Self::from_runs(runs),
+            Err(err) => {
+                println!("ERR: Unable to read history for scheduling: {}", err);
+                DurationEstimates::default()
+            }
+        }
+    }
+
+    // From a single run file, as stored in the history directory
+    pub fn from_file(filename : &Path) -> io::Result<DurationEstimates> {
+        Ok(Self::from_runs(vec![History::load(filename)?]))
+    }
+
+    // Runs are ordered oldest first
+    pub fn from_runs(runs : Vec<RunRecord>) -> DurationEstimates {
+        // Keyed by library and symbol, newer runs replace older durations
+        let mut cases : HashMap<(String, String), (String, f64)> = HashMap::new();
+        for case in runs.into_iter().flat_map(|run| run.cases) {
+            cases.insert((case.library, case.symbol), (case.module, case.duration_sec));
+        }
+
+        let mut modules : HashMap<(String, String), f64> = HashMap::new();
+        for ((library, _), (module, duration_sec)) in cases {
+            *modules.entry((library_file_name(&library), module)).or_default() += duration_sec;
+        }
+        let mean_sec = match modules.is_empty() {
+            true => 0.0,
+            false => modules.values().sum::<f64>() / modules.len() as f64,
+        };
+        DurationEstimates { modules, mean_sec }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    // A recorded duration exists for the module, see 'library_file_name'
+    pub fn is_known(&self, library : &str, module : &str) -> bool {
+        self.modules.contains_key(&(library.to_string(), module.to_string()))
+    }
+
+    // Expected duration of a module in seconds, see 'library_file_name'
+    pub fn module(&self, library : &str, module : &str) -> f64 {
+        match self.modules.get(&(library.to_string(), module.to_string())) {
+            Some(duration_sec) => *duration_sec,
+            None => self.mean_sec,
+        }
+    }
+
+    // Longest first, equal durations keep their relative order
+    // note: 'duration' is called from the sort comparator, it should be a cheap lookup
+    pub fn longest_first<T>(&self, items : &mut [T], duration : impl Fn(&T) -> f64) {
+        if self.is_empty() {
+            return;
+        }
+        items.sort_by(|a, b| duration(b).total_cmp(&duration(a)));
+    }
+}
+
+// The key of a library in the estimates, any directory is dropped
+pub fn library_file_name(library : &str) -> String {
+    match Path::new(library).file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => library.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_runner::CaseRecord;
+
+    fn run(cases : &[(&str, &str, &str, f64)]) -> RunRecord {
+        let mut run = RunRecord::new();
+        for (library, module, case, duration_sec) in cases {
+            let symbol = format!("test_{}_{}", module, case);
+            run.add(CaseRecord::new(library, module, case, &symbol, "pass", *duration_sec));
+        }
+        run
+    }
+
+    #[test]
+    fn no_runs_knows_nothing() {
+        let estimates = DurationEstimates::from_runs(Vec::new());
+        assert!(estimates.is_empty());
+        assert_eq!(estimates.module("libA.so", "a"), 0.0);
+
+        // Nothing known, the order is kept
+        let mut items = vec![1.0, 3.0, 2.0];
+        estimates.longest_first(&mut items, |d| *d);
+        assert_eq!(items, vec![1.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn module_is_sum_of_cases() {
+        let estimates = DurationEstimates::from_runs(vec![run(&[("libA.so", "a", "x", 1.0), ("libA.so", "a", "y", 2.0), ("libA.so", "b", "x", 0.5)])]);
+        assert_eq!(estimates.module("libA.so", "a"), 3.0);
+        assert_eq!(estimates.module("libA.so", "b"), 0.5);
+    }
+
+    #[test]
+    fn newer_runs_replace_case_durations() {
+        let older = run(&[("libA.so", "a", "x", 1.0), ("libA.so", "a", "y", 2.0)]);
+        // Only 'x' executed in the newer run, 'y' keeps its older duration
+        let newer = run(&[("libA.so", "a", "x", 4.0)]);
+        let estimates = DurationEstimates::from_runs(vec![older, newer]);
+        assert_eq!(estimates.module("libA.so", "a"), 6.0);
+    }
+
+    #[test]
+    fn libraries_match_on_file_name() {
+        let estimates = DurationEstimates::from_runs(vec![run(&[("/build/ci/lib/libA.so", "a", "x", 1.0), ("libB.so", "a", "x", 3.0)])]);
+        assert!(estimates.is_known(&library_file_name("/home/me/src/lib/libA.so"), "a"));
+        assert_eq!(estimates.module(&library_file_name("/home/me/src/lib/libA.so"), "a"), 1.0);
+        assert!(!estimates.is_known(&library_file_name("/build/ci/lib/libC.so"), "a"));
+    }
+
+    #[test]
+    fn unknown_module_is_the_mean() {
+        let estimates = DurationEstimates::from_runs(vec![run(&[("libA.so", "a", "x", 1.0), ("libA.so", "b", "x", 3.0)])]);
+        assert_eq!(estimates.module("libA.so", "c"), 2.0);
+        assert_eq!(estimates.module("libB.so", "a"), 2.0);
+    }
+
+    #[test]
+    fn longest_first_keeps_order_of_ties() {
+        let estimates = DurationEstimates::from_runs(vec![run(&[("libA.so", "a", "x", 1.0)])]);
+        let mut items = vec![("a", 1.0), ("b", 2.0), ("c", 1.0), ("d", 2.0)];
+        estimates.longest_first(&mut items, |(_, d)| *d);
+        assert_eq!(items, vec![("b", 2.0), ("d", 2.0), ("a", 1.0), ("c", 1.0)]);
+    }
+}
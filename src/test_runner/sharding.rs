@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use crate::test_runner::{Config, DurationEstimates, Singleton};
+
+//
+// Splitting a run over several runners (e.g. CI jobs), see --shard-index/--shard-count
+// Sharding is done per module so module main/exit and dependencies between cases stay on the same shard. The selected
+// modules of all libraries are sorted by library and module name and dealt round-robin, every runner given the same
+// libraries and selection gets the same partition.
+// With --shard-timings the modules are balanced on their durations in that run file instead, longest first to the
+// shard with the least work so far. The local history is never used for this, it differs between runners and the
+// shards would not agree on the partition; all shards must be given the same timings file.
+//
+// The Bazel test sharding variables are used when the options are not given:
+//  TEST_SHARD_INDEX, TEST_TOTAL_SHARDS and TEST_SHARD_STATUS_FILE (touched to tell sharding is supported)
+//
+#[derive(Debug, Clone, Copy)]
+pub struct Shard {
+    pub index : u32,
+    pub count : u32,
+}
+
+impl Shard {
+    // The shard of this run, None when not sharding
+    pub fn from_config() -> Result<Option<Shard>, String> {
+        let cfg = Config::instance();
+        let index = match cfg.shard_index {
+            Some(index) => Some(index),
+            None => env_number("TEST_SHARD_INDEX")?,
+        };
+        let count = match cfg.shard_count {
+            Some(count) => Some(count),
+            None => env_number("TEST_TOTAL_SHARDS")?,
+        };
+
+        let shard = match (index, count) {
+            (None, None) => return Ok(None),
+            (Some(index), Some(count)) if count > 0 && index < count => Shard { index, count },
+            (Some(index), Some(count)) => return Err(format!("shard index {} out of range for {} shards", index, count)),
+            _ => return Err("both a shard index and a shard count are needed".to_string()),
+        };
+
+        if let Ok(filename) = env::var("TEST_SHARD_STATUS_FILE") {
+            if let Err(err) = OpenOptions::new().create(true).append(true).open(&filename) {
+                println!("ERR: Unable to touch shard status file '{}': {}", filename, err);
+            }
+        }
+        Ok(Some(shard))
+    }
+
+    // Returns the library and module names of this shard out of the selected modules of all libraries
+    // note: libraries are given by file name to match the timings, see 'library_file_name'
+    pub fn select(&self, mut modules : Vec<(String, String)>, estimates : &DurationEstimates) -> HashSet<(String, String)> {
+        modules.sort();
+        if estimates.is_empty() {
+            return modules
+                .into_iter()
+                .enumerate()
+                .filter(|(position, _)| position % self.count as usize == self.index as usize)
+                .map(|(_, module)| module)
+                .collect();
+        }
+
+        let mut modules : Vec<(f64, (String, String))> = modules
+            .into_iter()
+            .map(|(library, module)| (estimates.module(&library, &module), (library, module)))
+            .collect();
+        estimates.longest_first(&mut modules, |(duration_sec, _)| *duration_sec);
+        let mut shard_durations = vec![0.0f64; self.count as usize];
+        let mut selected = HashSet::new();
+        for (duration_sec, (library, module)) in modules {
+            // The first of equally loaded shards, keeps the partition deterministic
+            let (shard, _) = shard_durations.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+            shard_durations[shard] += duration_sec;
+            if shard == self.index as usize {
+                selected.insert((library, module));
+            }
+        }
+        selected
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+fn env_number(name : &str) -> Result<Option<u32>, String> {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|_| format!("invalid {} '{}'", name, value)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_runner::{CaseRecord, RunRecord};
+
+    fn modules(names : &[(&str, &str)]) -> Vec<(String, String)> {
+        names.iter().map(|(library, module)| (library.to_string(), module.to_string())).collect()
+    }
+
+    fn timings(modules : &[(&str, &str, f64)]) -> DurationEstimates {
+        let mut run = RunRecord::new();
+        for (library, module, duration_sec) in modules {
+            let symbol = format!("test_{}_case", module);
+            run.add(CaseRecord::new(library, module, "case", &symbol, "pass", *duration_sec));
+        }
+        DurationEstimates::from_runs(vec![run])
+    }
+
+    fn select_all(count : u32, modules : &[(String, String)], estimates : &DurationEstimates) -> Vec<HashSet<(String, String)>> {
+        (0..count).map(|index| Shard { index, count }.select(modules.to_vec(), estimates)).collect()
+    }
+
+    #[test]
+    fn round_robin_without_timings() {
+        let estimates = DurationEstimates::default();
+        // Given unsorted, dealt in sorted order
+        let all = modules(&[("libB.so", "a"), ("libA.so", "b"), ("libA.so", "a"), ("libA.so", "c")]);
+        let shards = select_all(2, &all, &estimates);
+        assert_eq!(shards[0], modules(&[("libA.so", "a"), ("libA.so", "c")]).into_iter().collect());
+        assert_eq!(shards[1], modules(&[("libA.so", "b"), ("libB.so", "a")]).into_iter().collect());
+    }
+
+    #[test]
+    fn more_shards_than_modules() {
+        let estimates = DurationEstimates::default();
+        let all = modules(&[("libA.so", "a")]);
+        let shards = select_all(3, &all, &estimates);
+        assert_eq!(shards[0].len(), 1);
+        assert!(shards[1].is_empty());
+        assert!(shards[2].is_empty());
+    }
+
+    #[test]
+    fn balanced_on_timings() {
+        let estimates = timings(&[("libA.so", "a", 5.0), ("libA.so", "b", 3.0), ("libA.so", "c", 2.0), ("libA.so", "d", 1.0)]);
+        let all = modules(&[("libA.so", "a"), ("libA.so", "b"), ("libA.so", "c"), ("libA.so", "d")]);
+        let shards = select_all(2, &all, &estimates);
+        // a(5) -> 0, b(3) -> 1, c(2) -> 1, d(1) -> 0 : 6 / 5
+        assert_eq!(shards[0], modules(&[("libA.so", "a"), ("libA.so", "d")]).into_iter().collect());
+        assert_eq!(shards[1], modules(&[("libA.so", "b"), ("libA.so", "c")]).into_iter().collect());
+    }
+
+    #[test]
+    fn ties_are_deterministic() {
+        let estimates = timings(&[("libA.so", "a", 1.0), ("libA.so", "b", 1.0), ("libA.so", "c", 1.0), ("libA.so", "d", 1.0)]);
+        let all = modules(&[("libA.so", "d"), ("libA.so", "c"), ("libA.so", "b"), ("libA.so", "a")]);
+        let mut reversed = all.clone();
+        reversed.reverse();
+        // Equal durations are taken in name order, equally loaded shards in index order
+        for given in [&all, &reversed] {
+            let shards = select_all(2, given, &estimates);
+            assert_eq!(shards[0], modules(&[("libA.so", "a"), ("libA.so", "c")]).into_iter().collect());
+            assert_eq!(shards[1], modules(&[("libA.so", "b"), ("libA.so", "d")]).into_iter().collect());
+        }
+    }
+
+    #[test]
+    fn unknown_modules_take_the_mean() {
+        // 'c' is not in the timings and counts as the mean (2.0)
+        let estimates = timings(&[("libA.so", "a", 3.0), ("libA.so", "b", 1.0)]);
+        let all = modules(&[("libA.so", "a"), ("libA.so", "b"), ("libA.so", "c")]);
+        let shards = select_all(2, &all, &estimates);
+        // a(3) -> 0, c(2) -> 1, b(1) -> 1
+        assert_eq!(shards[0], modules(&[("libA.so", "a")]).into_iter().collect());
+        assert_eq!(shards[1], modules(&[("libA.so", "b"), ("libA.so", "c")]).into_iter().collect());
+    }
+
+    #[test]
+    fn every_module_in_exactly_one_shard() {
+        let estimates = timings(&[("libA.so", "a", 4.0), ("libB.so", "a", 2.5)]);
+        let all = modules(&[("libA.so", "a"), ("libA.so", "b"), ("libB.so", "a"), ("libB.so", "b"), ("libC.so", "x")]);
+        let shards = select_all(3, &all, &estimates);
+        for module in &all {
+            assert_eq!(shards.iter().filter(|shard| shard.contains(module)).count(), 1);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::{env, fs, io, process};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+//
+// A fresh temporary directory, created for each test function execution
+// Directories are named 'tstrust-<pid>-<counter>-<name>' in the system temp directory.
+//
+#[derive(Debug)]
+pub struct TempDir {
+    path : PathBuf,
+}
+
+impl TempDir {
+    pub fn create(name : &str) -> io::Result<TempDir> {
+        // Keep the name readable but file system friendly, symbols can contain parameter names
+        let name : String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect();
+
+        loop {
+            let counter = TEMP_DIR_COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = env::temp_dir().join(format!("tstrust-{}-{}-{}", process::id(), counter, name));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(TempDir { path }),
+                // Left-over from a previous run with the same pid, just try the next one
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Remove the directory and everything the test left in it
+    pub fn remove(self) -> io::Result<()> {
+        fs::remove_dir_all(&self.path)
+    }
+
+    // Keep the directory on disk and hand back the path
+    pub fn keep(self) -> PathBuf {
+        self.path
+    }
+}
@@ -1,10 +1,11 @@
 use std::cell::RefCell;
-use std::ffi::{c_char, c_int, c_void, CStr};
+use std::env;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::rc::Rc;
 use std::sync::Mutex;
 use std::time::{Instant};
-use libc::pthread_exit;
 use libloading::Symbol;
 use once_cell::sync::Lazy;
 use crate::test_runner::*;
@@ -40,6 +41,12 @@ pub struct TestFunction {
 
     state : State,
     pub dependencies : Vec<TestFunctionRef>,
+    // Set when this is one instance of a parameterized case, see 'case_params'
+    pub param : Option<CaseParam>,
+    // Cleared when the case is filtered out by the selection (e.g. --rerun-failed)
+    pub selected : bool,
+    // Set for death tests, the case passes only if it terminates as expected, see 'expect_death'
+    pub death : Option<DeathExpectation>,
     pub test_result: TestResult,
 }
 pub type TestFunctionRef = Rc<RefCell<TestFunction>>;
@@ -47,6 +54,9 @@ pub type TestFunctionRef = Rc<RefCell<TestFunction>>;
 struct ThreadArg {
     symbol : String,
     dynlib : DynLibraryRef,
+    // Benchmark mode, number of warm-up and timed iterations, zero iterations is a regular execution
+    bench_warmup : u32,
+    bench_iterations : u32,
 }
 
 impl ThreadArg {
@@ -54,6 +64,8 @@ impl ThreadArg {
         Self {
             symbol : String::new(),
             dynlib : dynlib.clone(),
+            bench_warmup : 0,
+            bench_iterations : 0,
         }
     }
 }
@@ -87,29 +99,72 @@ extern "C" fn dependency_handler(name : *const c_char, dep_list: *const c_char)
     CONTEXT.lock().unwrap().add_dependency(str_name, str_deplist);
 }
 
-extern "C" fn all_log_handlers(line : c_int, file: *const c_char, format: *const c_char) {
+extern "C" fn case_params_handler(name : *const c_char, param_list: *const c_char) {
+    let str_name = unsafe { CStr::from_ptr(name).to_str().expect("case params impl, name error") };
+    let str_paramlist = unsafe { CStr::from_ptr(param_list).to_str().expect("case params impl, param list error") };
+
+    CONTEXT.lock().unwrap().add_case_params(str_name, str_paramlist);
+}
+
+// Declares a case as a death test, negative signal/exit status means any, null/empty pattern skips the stderr check
+extern "C" fn expect_death_handler(name : *const c_char, signal : c_int, exit_status : c_int, stderr_pattern : *const c_char) {
+    let str_name = unsafe { CStr::from_ptr(name).to_str().expect("expect death impl, name error") };
+    let str_pattern = match stderr_pattern.is_null() {
+        true => None,
+        false => Some(unsafe { CStr::from_ptr(stderr_pattern).to_str().expect("expect death impl, pattern error") }),
+    };
+    CONTEXT.lock().unwrap().add_death_expectation(str_name, DeathExpectation::new(signal, exit_status, str_pattern));
+}
+
+// Returns the value of the current parameter set, or null if the case is not parameterized
+// note: the string is owned by the context and valid until the test function returns
+extern "C" fn get_case_param_handler() -> *const c_char {
+    match &CONTEXT.lock().unwrap().current_param {
+        Some(param) => param.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+// Returns the runner parameter for a key, undefined keys are recorded on the test result and null is returned
+extern "C" fn get_param_handler(key : *const c_char) -> *const c_char {
+    let str_key = unsafe { CStr::from_ptr(key).to_str().expect("get param impl, key error") };
+
+    match PARAMS.get(str_key) {
+        Some(value) => value.as_ptr(),
+        None => {
+            let mut ctx = CONTEXT.lock().unwrap();
+            if !ctx.undefined_params.iter().any(|k| k == str_key) {
+                ctx.undefined_params.push(str_key.to_string());
+            }
+            ptr::null()
+        }
+    }
+}
+
+extern "C-unwind" fn all_log_handlers(line : c_int, file: *const c_char, format: *const c_char) {
     let str_file = unsafe { CStr::from_ptr(file).to_str().expect("assert error impl, file error") };
     let str_msg = unsafe { CStr::from_ptr(format).to_str().expect("assert error impl, exp error") };
 
     println!("log: {}:{}:{}", str_file, line, str_msg);
 
 }
+// Note: handlers terminating the test thread with 'pthread_exit' must be 'C-unwind', the forced unwind
+//       from 'pthread_exit' would otherwise abort the whole process when passing through them.
 // FIXME: when rust support c-variadic's
 // Change to: unsafe extern "C" fn fatal_handler(line : c_int, file: *const c_char, format: *const c_char, ...) {
 // see: https://github.com/rust-lang/rust/issues/44930
-extern "C" fn fatal_handler(line : c_int, file: *const c_char, format: *const c_char) {
+extern "C-unwind" fn fatal_handler(line : c_int, file: *const c_char, format: *const c_char) {
     let str_exp = unsafe { CStr::from_ptr(format).to_str().expect("assert error impl, exp error") };
     let str_file = unsafe { CStr::from_ptr(file).to_str().expect("assert error impl, file error") };
 
-    let func_error = TestFuncError::new(TestFuncErrorClass::Fatal, str_file, line as u32, str_exp);
+    let func_error = TestFuncError::new(TestFuncErrorClass::Fatal, str_file, line as u32, str_exp)
+        .with_backtrace(symbolize(&capture_test_frames()));
     func_error.print();
     CONTEXT.lock().unwrap().func_error = Some(func_error);
 
-    unsafe {
-        pthread_exit(ptr::null_mut());
-    }
+    exit_test_thread();
 }
-extern "C" fn error_handler(line : c_int, file: *const c_char, format: *const c_char) {
+extern "C-unwind" fn error_handler(line : c_int, file: *const c_char, format: *const c_char) {
     let str_exp = unsafe { CStr::from_ptr(format).to_str().expect("assert error impl, exp error") };
     let str_file = unsafe { CStr::from_ptr(file).to_str().expect("assert error impl, file error") };
 
@@ -117,26 +172,23 @@ extern "C" fn error_handler(line : c_int, file: *const c_char, format: *const c_
     func_error.print();
     CONTEXT.lock().unwrap().func_error = Some(func_error);
 
-    unsafe {
-        pthread_exit(ptr::null_mut());
-    }
+    exit_test_thread();
 }
 
-extern "C" fn abort_handler(line : c_int, file: *const c_char, format: *const c_char) {
+extern "C-unwind" fn abort_handler(line : c_int, file: *const c_char, format: *const c_char) {
     let str_exp = unsafe { CStr::from_ptr(format).to_str().expect("assert error impl, exp error") };
     let str_file = unsafe { CStr::from_ptr(file).to_str().expect("assert error impl, file error") };
 
-    let func_error = TestFuncError::new(TestFuncErrorClass::Abort, str_file, line as u32, str_exp);
+    let func_error = TestFuncError::new(TestFuncErrorClass::Abort, str_file, line as u32, str_exp)
+        .with_backtrace(symbolize(&capture_test_frames()));
     func_error.print();
     CONTEXT.lock().unwrap().func_error = Some(func_error);
 
-    unsafe {
-        pthread_exit(ptr::null_mut());
-    }
+    exit_test_thread();
 }
 
 
-extern "C" fn assert_error_handler(exp : *const c_char, file : *const c_char, line : c_int) {
+extern "C-unwind" fn assert_error_handler(exp : *const c_char, file : *const c_char, line : c_int) {
 
     let str_exp = unsafe { CStr::from_ptr(exp).to_str().expect("assert error impl, exp error") };
     let str_file = unsafe { CStr::from_ptr(file).to_str().expect("assert error impl, file error") };
@@ -148,10 +200,25 @@ extern "C" fn assert_error_handler(exp : *const c_char, file : *const c_char, li
     func_error.print();
     CONTEXT.lock().unwrap().func_error = Some(func_error);
 
+    exit_test_thread();
+
+}
+
+// Resource usage is measured on the test thread, so this must be called before the thread ends
+fn record_resource_usage() {
+    let end = UsageSnapshot::take();
+    let mut ctx = CONTEXT.lock().unwrap();
+    if let Some(start) = &ctx.usage_start {
+        ctx.resource_usage = Some(ResourceUsage::between(start, &end));
+    }
+}
+
+// Terminate the test thread, used by the error handlers - execution continues after the join in 'execute_attempt'
+pub fn exit_test_thread() -> ! {
+    record_resource_usage();
     unsafe {
         pthread_exit(ptr::null_mut());
     }
-
 }
 
 pub fn get_truninterface_ptr() -> TestRunnerInterface {
@@ -164,6 +231,12 @@ pub fn get_truninterface_ptr() -> TestRunnerInterface {
     trun_interface.fatal = Some(fatal_handler);
     trun_interface.abort = Some(abort_handler);
     trun_interface.case_depends = Some(dependency_handler);
+    trun_interface.case_params = Some(case_params_handler);
+    trun_interface.get_case_param = Some(get_case_param_handler);
+    trun_interface.get_param = Some(get_param_handler);
+    trun_interface.get_temp_dir = Some(get_temp_dir_handler);
+    trun_interface.get_data_dir = Some(get_data_dir_handler);
+    trun_interface.expect_death = Some(expect_death_handler);
     trun_interface.assert_error = Some(assert_error_handler);
     trun_interface.set_pre_case_callback = Some(set_pre_case_handler);
     trun_interface.set_post_case_callback = Some(set_post_case_handler);
@@ -171,7 +244,23 @@ pub fn get_truninterface_ptr() -> TestRunnerInterface {
     return trun_interface;
 }
 
-extern "C" fn pthread_execute_async(ptr_arg: *mut c_void) -> *mut c_void {
+// Returns the temporary directory of the executing test function, valid until the test function returns
+extern "C" fn get_temp_dir_handler() -> *const c_char {
+    match &CONTEXT.lock().unwrap().temp_dir {
+        Some(temp_dir) => temp_dir.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+// Returns the test data directory for the library/module being executed
+extern "C" fn get_data_dir_handler() -> *const c_char {
+    match &CONTEXT.lock().unwrap().data_dir {
+        Some(data_dir) => data_dir.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+extern "C-unwind" fn pthread_execute_async(ptr_arg: *mut c_void) -> *mut c_void {
     let thread_arg : &mut ThreadArg = unsafe { &mut *(ptr_arg as *mut ThreadArg)};
 
     // do this on a two liner - otherwise the borrow checker will terminate at the end of the statement
@@ -181,12 +270,24 @@ extern "C" fn pthread_execute_async(ptr_arg: *mut c_void) -> *mut c_void {
     let dynlib = thread_arg.dynlib.as_ref().borrow();
     let func : Symbol<TestableFunction> = dynlib.get_testable_function(&thread_arg.symbol);
 
+    // Crash signals on this thread terminates the thread, see 'CrashGuard'
+    let _thread_guard = Config::instance().catch_signals.then(TestThreadGuard::enter);
+
+    CONTEXT.lock().unwrap().usage_start = Some(UsageSnapshot::take());
+
     // Fetch a callback interface instance, treat as a pointer and off we go...
     let mut trun_interface = get_truninterface_ptr(); //TestRunnerInterface::new();
+    if thread_arg.bench_iterations > 0 {
+        execute_bench(&func, &mut trun_interface, thread_arg.bench_warmup, thread_arg.bench_iterations);
+        record_resource_usage();
+        return std::ptr::null_mut();
+    }
     let raw_result = unsafe {
         func(&mut trun_interface)
     };
 
+    record_resource_usage();
+
     // Set the raw result - if any...
     // note: in case of errors, the thread is terminated, the result handling will first check if we
     //       have any errors before checking the resulting test-code..
@@ -196,6 +297,31 @@ extern "C" fn pthread_execute_async(ptr_arg: *mut c_void) -> *mut c_void {
     return std::ptr::null_mut();
 }
 
+// Benchmark loop, each timed iteration is stored in the context
+// note: we stop at the first failing iteration, asserts terminate the thread as usual
+fn execute_bench(func : &Symbol<TestableFunction>, trun_interface : &mut TestRunnerInterface, warmup : u32, iterations : u32) {
+    let mut raw_result = 0;
+    for _ in 0..warmup {
+        raw_result = unsafe { func(trun_interface) };
+        if raw_result != TestReturnCode::Pass as c_int {
+            CONTEXT.lock().unwrap().raw_result = raw_result;
+            return;
+        }
+    }
+    for _ in 0..iterations {
+        let t_start = Instant::now();
+        raw_result = unsafe { func(trun_interface) };
+        let duration = t_start.elapsed();
+
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.bench_samples.push(duration);
+        if raw_result != TestReturnCode::Pass as c_int {
+            break;
+        }
+    }
+    CONTEXT.lock().unwrap().raw_result = raw_result;
+}
+
 impl TestFunction {
     pub fn new(symbol : &str, module : &str, case : &str) -> TestFunctionRef {
         let mut test_scope = TestScope::Module;
@@ -222,7 +348,7 @@ impl TestFunction {
             }
         }
 
-        let test_function = TestFunction {
+        let mut test_function = TestFunction {
             case_name: case.to_string(),
             module_name: new_module_name,
             symbol : symbol.to_string(),
@@ -231,10 +357,41 @@ impl TestFunction {
 
             state : State::Idle,
             dependencies : Vec::new(),
+            param : None,
+            selected : true,
+            death : None,
             test_result : TestResult::new(),
         };
+        if matches!(test_function.test_type, TestType::Regular) && case.starts_with(DEATH_CASE_PREFIX) {
+            test_function.death = Some(DeathExpectation::default());
+        }
         return Rc::new(RefCell::new(test_function));
     }
+
+    // Create a new instance of this case bound to a specific parameter set
+    pub fn with_param(&self, param : &CaseParam) -> TestFunctionRef {
+        let func = TestFunction::new(&self.symbol, &self.module_name, &self.case_name);
+        func.borrow_mut().param = Some(param.clone());
+        func.borrow_mut().selected = self.selected;
+        func.borrow_mut().death = self.death.clone();
+        func
+    }
+
+    // Case name including the parameter set, i.e. 'case[param]'
+    pub fn display_name(&self) -> String {
+        match &self.param {
+            Some(param) => format!("{}[{}]", self.case_name, param.name),
+            None => self.case_name.clone(),
+        }
+    }
+
+    // Symbol including the parameter set, i.e. 'test_module_case[param]'
+    pub fn display_symbol(&self) -> String {
+        match &self.param {
+            Some(param) => format!("{}[{}]", self.symbol, param.name),
+            None => self.symbol.clone(),
+        }
+    }
     pub fn should_execute(&self) -> bool {
         let cfg = Config::instance();
         // already executed?
@@ -244,8 +401,12 @@ impl TestFunction {
             _ => (),
         }
 
+        if !self.selected {
+            return false;
+        }
+
         // Are we part of execution chain?
-        if cfg.testcases.contains(&"-".to_string()) || cfg.testcases.contains(&self.case_name) {
+        if cfg.testcases.contains(&"-".to_string()) || cfg.testcases.contains(&self.case_name) || cfg.testcases.contains(&self.display_name()) {
             return true;
         }
 
@@ -265,6 +426,14 @@ impl TestFunction {
         }
     }
 
+    // Bring the function back to idle so it can be executed again (see --repeat)
+    // note: dependencies are cleared as module main will declare them again
+    pub fn reset(&mut self) {
+        self.change_state(State::Idle);
+        self.dependencies.clear();
+        self.test_result = TestResult::new();
+    }
+
     fn change_state(&mut self, new_state : State) {
         self.state = new_state;
     }
@@ -305,8 +474,54 @@ impl TestFunction {
         self.change_state(State::Executing);
         self.execute_dependencies(module, library);
 
+        let library_name = library.borrow().name.clone();
+        RUN_JOURNAL.lock().unwrap().case_started(&library_name, &self.display_symbol());
+        set_exit_interception(true);
+
+        // Failing test cases are re-executed up to '--retries' times, every attempt is kept on the final result
+        // Main/exit (setup/teardown) are executed once
+        let max_attempts = match self.test_type {
+            TestType::Regular => Config::instance().retries + 1,
+            _ => 1,
+        };
+        let mut attempts : Vec<TestResult> = Vec::new();
+        loop {
+            let mut test_result = self.execute_attempt(module, library);
+            let is_final = !test_result.did_fail() || (attempts.len() as u32 + 1) >= max_attempts;
+            if !is_final {
+                test_result.print();
+                attempts.push(test_result);
+                println!("=== RETRY\t{} (attempt {}/{})", self.display_symbol(), attempts.len() + 1, max_attempts);
+                continue;
+            }
+
+            // Passing after one or more failed attempts is flaky, not a plain pass
+            test_result.flaky = !attempts.is_empty() && !test_result.did_fail();
+            test_result.attempts = attempts;
+            test_result.print();
+            set_exit_interception(false);
+            RUN_JOURNAL.lock().unwrap().case_finished(&library_name, &self.display_symbol(), test_result.outcome(), test_result.exec_duration);
+            if test_result.did_fail() {
+                record_failure(Config::instance().failure_limit());
+            }
+            self.test_result = test_result;
+            break;
+        }
+
+        self.change_state(State::Finished);
+    }
+
+    // Execute the function once, including pre/post case handlers
+    fn execute_attempt(&self, module : &Module, library : &DynLibraryRef) -> TestResult {
+        let mut test_result = TestResult::new();
+
         // Spawn thread here, need to figure out what happens with the Context (since it is a thread-local) variable
-        println!("=== RUN \t{}",self.symbol);
+        println!("=== RUN \t{}",self.display_symbol());
+
+        // Fresh temporary directory for this execution, created before the timer is started
+        let temp_dir = TempDir::create(&self.display_symbol())
+            .map_err(|err| println!("ERR: Unable to create temp dir: {}", err))
+            .ok();
 
         // Start the timer - we do NOT include 'dependencies' in the timing - they are just a way of controlling execution
         let t_start = Instant::now();
@@ -315,8 +530,86 @@ impl TestFunction {
         // Reset the context, this must be done before the pre/post cases are executed, they all use the context to communicate!
         let mut ctx = CONTEXT.lock().unwrap();
         ctx.reset();
+        ctx.set_current_param(self.param.as_ref());
+        ctx.temp_dir = temp_dir.as_ref().and_then(|dir| CString::new(dir.path().to_string_lossy().as_bytes()).ok());
+        ctx.data_dir = CString::new(self.data_dir(library).to_string_lossy().as_bytes()).ok();
         drop(ctx);
 
+        // Pre/Post case handlers are considered part of the test, so they also execute in the temp dir
+        let prev_cwd = match &temp_dir {
+            Some(dir) if Config::instance().chdir_temp => Self::change_dir(dir.path()),
+            _ => None,
+        };
+
+
+        // Set up the thread argument..
+        let mut thread_arg = ThreadArg::new(library);
+        thread_arg.symbol = self.symbol.clone();
+        if self.is_benchmarked() {
+            thread_arg.bench_warmup = Config::instance().bench_warmup;
+            thread_arg.bench_iterations = Config::instance().bench_iterations;
+        }
+
+        if let Some(expectation) = &self.death {
+            test_result.limit_violation = Self::execute_death(module, thread_arg, expectation);
+        } else if self.is_isolated() {
+            test_result.limit_violation = Self::execute_isolated(module, thread_arg);
+        } else {
+            Self::execute_in_process(module, thread_arg, Config::instance().catch_signals);
+        }
+
+        // Stop timer
+        test_result.exec_duration = t_start.elapsed();
+
+        if let Some(cwd) = prev_cwd {
+            Self::change_dir(&cwd);
+        }
+
+        // Create test result
+        let mut ctx = CONTEXT.lock().unwrap();
+        test_result.func_error = ctx.func_error.take();
+        test_result.undefined_params = std::mem::take(&mut ctx.undefined_params);
+        test_result.resource_usage = ctx.resource_usage.take();
+        test_result.check_issues = std::mem::take(&mut ctx.check_issues);
+
+
+        Self::handle_test_return(&mut test_result, ctx.raw_result);
+        if test_result.limit_violation.is_some() || test_result.check_issues.iter().any(|issue| issue.is_failure) {
+            test_result.return_code = Some(TestReturnCode::Fail);
+        }
+        test_result.symbol = self.display_symbol();
+        if self.is_benchmarked() {
+            test_result.bench = BenchStats::from_samples(&ctx.bench_samples);
+            Self::check_bench_baseline(&mut test_result, library);
+        }
+        test_result.module_name = self.module_name.clone();
+        test_result.case_name = self.case_name.clone();
+        test_result.temp_dir = temp_dir.and_then(|dir| Self::keep_or_remove_temp_dir(&test_result, dir));
+
+        test_result
+    }
+
+
+    // Pre case handler, the test function on its own thread and the post case handler
+    fn execute_in_process(module : &Module, thread_arg : ThreadArg, catch_signals : bool) {
+        let cfg = Config::instance();
+        let fd_snapshot = match cfg.is_fd_leak_check_enabled() {
+            true => FdSnapshot::take().map_err(|err| println!("ERR: Unable to read open file descriptors: {}", err)).ok(),
+            false => None,
+        };
+        let thread_snapshot = match cfg.is_thread_leak_check_enabled() {
+            true => ThreadSnapshot::take().map_err(|err| println!("ERR: Unable to read process threads: {}", err)).ok(),
+            false => None,
+        };
+        let global_state = match cfg.is_global_state_check_enabled() {
+            true => Some(GlobalStateSnapshot::take()),
+            false => None,
+        };
+        // Started last, the snapshots above allocates memory
+        #[cfg(feature = "heap-leak-check")]
+        if cfg.is_heap_leak_check_enabled() {
+            HeapTracker::start(cfg.heap_leak_backtraces);
+        }
 
         // Note: We do this here - as we align to the existing C/C++ test runner
         //       otherwise we could simply run in the module it-self (which might have been more prudent)
@@ -327,15 +620,24 @@ impl TestFunction {
             module.pre_case_func.as_ref().unwrap()(&mut trun_interface);
         }
 
-        // Set up the thread argument..
-        let mut thread_arg = ThreadArg::new(library);
-        thread_arg.symbol = self.symbol.clone();
-
         // Spawn execution thread
         let mut mthread = PThread::<ThreadArg>::new(thread_arg);
         // FIXME: better error handling, this will just panic if something goes wrong...
+        let crash_guard = catch_signals.then(CrashGuard::install);
+        // Thread creation allocates (TLS) on the first use, that's not the test's doing
+        #[cfg(feature = "heap-leak-check")]
+        HeapTracker::untracked(|| mthread.spawn(pthread_execute_async).ok());
+        #[cfg(not(feature = "heap-leak-check"))]
         mthread.spawn(pthread_execute_async).ok();
         mthread.join().ok();
+        drop(crash_guard);
+
+        if let Some(crash) = CrashGuard::take_crash() {
+            let func_error = TestFuncError::new(TestFuncErrorClass::Crash, "", 0, &crash.to_string())
+                .with_backtrace(symbolize(&crash.frames));
+            func_error.print();
+            CONTEXT.lock().unwrap().func_error = Some(func_error);
+        }
 
         // Execute post case handler - if any...
         if module.post_case_func.is_some() {
@@ -343,21 +645,167 @@ impl TestFunction {
             module.post_case_func.as_ref().unwrap()(&mut trun_interface);
         }
 
-        // Stop timer
-        self.test_result.exec_duration = t_start.elapsed();
+        #[cfg(feature = "heap-leak-check")]
+        if cfg.is_heap_leak_check_enabled() {
+            let issues = HeapTracker::check_leaks(cfg.fail_on_heap_leaks);
+            CONTEXT.lock().unwrap().check_issues.extend(issues);
+        }
+        if let Some(before) = fd_snapshot {
+            let issues = FdSnapshot::check_leaks(&before, cfg.fail_on_fd_leaks);
+            CONTEXT.lock().unwrap().check_issues.extend(issues);
+        }
+        if let Some(before) = thread_snapshot {
+            let issues = ThreadSnapshot::check_leaks(&before, cfg.fail_on_thread_leaks);
+            CONTEXT.lock().unwrap().check_issues.extend(issues);
+        }
+        if let Some(before) = global_state {
+            let issues = GlobalStateSnapshot::check_changes(&before, cfg.fail_on_global_state);
+            CONTEXT.lock().unwrap().check_issues.extend(issues);
+        }
+    }
+
+    // Same as 'execute_in_process' but in a child process with the resource limits applied
+    // The outcome of the child is transferred to the context, so result handling is the same in both cases.
+    fn execute_isolated(module : &Module, thread_arg : ThreadArg) -> Option<LimitViolation> {
+        let limits = ResourceLimits::from_config();
+        let res = run_isolated(&limits, || Self::execute_child(module, thread_arg, Config::instance().catch_signals));
 
-        // Create test result
         let mut ctx = CONTEXT.lock().unwrap();
-        self.test_result.func_error = ctx.func_error.take();
+        let (outcome, usage) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Fatal, "", 0, &format!("unable to execute in child process: {}", err)));
+                return None;
+            }
+        };
 
+        let violation = limits.violation(&outcome, &usage);
+        match outcome {
+            ChildOutcome::Completed(result) => {
+                ctx.raw_result = result.raw_result;
+                ctx.func_error = result.func_error;
+                ctx.undefined_params = result.undefined_params;
+                ctx.resource_usage = result.resource_usage;
+                ctx.bench_samples = result.bench_samples;
+                ctx.check_issues = result.check_issues;
+            },
+            // Same class as a crash caught in-process (--catch-signals), whether or not the case was isolated
+            ChildOutcome::Crashed { signal, frames, .. } => {
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Crash, "", 0, &format!("crashed with {}", signal_name(signal)))
+                    .with_backtrace(symbolize(&frames)));
+            },
+            ChildOutcome::Signaled { signal } => {
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Crash, "", 0, &format!("terminated by {}", signal_name(signal))));
+            },
+            ChildOutcome::Exited { status } => {
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Fatal, "", 0, &format!("exited with status {}", status)));
+            },
+        }
+        violation
+    }
 
-        self.handle_test_return(ctx.raw_result);
-        self.test_result.symbol = self.symbol.clone();
+    // Death test, executed in a child process which must terminate as expected
+    // note: crash signals are never caught in-process here, they are the point of the test
+    fn execute_death(module : &Module, thread_arg : ThreadArg, expectation : &DeathExpectation) -> Option<LimitViolation> {
+        let limits = ResourceLimits::from_config();
+        let res = run_isolated_capture_stderr(&limits, || {
+            set_exit_interception(false);
+            Self::execute_child(module, thread_arg, false)
+        });
 
-        self.test_result.print();
-        self.change_state(State::Finished);
+        let mut ctx = CONTEXT.lock().unwrap();
+        let (outcome, usage, stderr) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Fatal, "", 0, &format!("unable to execute in child process: {}", err)));
+                return None;
+            }
+        };
+
+        // Terminating as expected is a pass, even if a limit was involved
+        let mut violation = None;
+        match expectation.check(&outcome, &stderr) {
+            Ok(()) => ctx.raw_result = TestReturnCode::Pass as c_int,
+            Err(reason) => {
+                violation = limits.violation(&outcome, &usage);
+                ctx.func_error = Some(TestFuncError::new(TestFuncErrorClass::Fatal, "", 0, &reason));
+            },
+        }
+        // The case returned, keep what the checkers and parameters reported
+        if let ChildOutcome::Completed(result) = outcome {
+            ctx.undefined_params = result.undefined_params;
+            ctx.resource_usage = result.resource_usage;
+            ctx.check_issues = result.check_issues;
+        }
+        violation
+    }
+
+    // Executed in the child process, everything the runner needs is returned
+    fn execute_child(module : &Module, thread_arg : ThreadArg, catch_signals : bool) -> ChildResult {
+        Self::execute_in_process(module, thread_arg, catch_signals);
+        let mut ctx = CONTEXT.lock().unwrap();
+        ChildResult {
+            raw_result : ctx.raw_result,
+            func_error : ctx.func_error.take(),
+            undefined_params : std::mem::take(&mut ctx.undefined_params),
+            resource_usage : ctx.resource_usage.take(),
+            bench_samples : std::mem::take(&mut ctx.bench_samples),
+            check_issues : std::mem::take(&mut ctx.check_issues),
+            ..Default::default()
+        }
+    }
+
+    // Only regular cases are executed in a child process, main/exit declare things for the module and must run in the runner
+    fn is_isolated(&self) -> bool {
+        matches!(self.test_type, TestType::Regular) && (Config::instance().isolate || !ResourceLimits::from_config().is_empty())
+    }
+
+    // Only regular cases are benchmarked, main/exit are setup and teardown
+    fn is_benchmarked(&self) -> bool {
+        Config::instance().bench && matches!(self.test_type, TestType::Regular)
+    }
+
+    // Fail the test if it is slower than the baseline
+    fn check_bench_baseline(test_result : &mut TestResult, library : &DynLibraryRef) {
+        let Some(bench) = &test_result.bench else {
+            return;
+        };
+        let Some(baseline) = BENCH_BASELINE.find(&library.borrow().name, &test_result.symbol) else {
+            return;
+        };
+        test_result.bench_regression = bench.regression_from(baseline, Config::instance().bench_threshold);
+        if test_result.bench_regression.is_some() {
+            test_result.return_code = Some(TestReturnCode::Fail);
+        }
     }
 
+    fn data_dir(&self, library : &DynLibraryRef) -> PathBuf {
+        let module_name = if self.is_global() { "" } else { self.module_name.as_str() };
+        resolve_data_dir(&library.borrow().name, module_name)
+    }
+
+    // Returns the previous working directory if successful
+    fn change_dir(dir : &Path) -> Option<PathBuf> {
+        let prev_cwd = env::current_dir().ok()?;
+        match env::set_current_dir(dir) {
+            Ok(()) => Some(prev_cwd),
+            Err(err) => {
+                println!("ERR: Unable to change directory to '{}': {}", dir.display(), err);
+                None
+            }
+        }
+    }
+
+    // Temp dirs are kept for failed tests (for post-mortem) or if asked to, the path is returned in that case
+    fn keep_or_remove_temp_dir(test_result : &TestResult, temp_dir : TempDir) -> Option<PathBuf> {
+        if Config::instance().keep_temp || test_result.did_fail() {
+            return Some(temp_dir.keep());
+        }
+        if let Err(err) = temp_dir.remove() {
+            println!("ERR: Unable to remove temp dir: {}", err);
+        }
+        None
+    }
 
     fn execute_dependencies(&mut self, module : &Module, dynlib : &DynLibraryRef) {
         for func in &self.dependencies {
@@ -372,13 +820,13 @@ impl TestFunction {
         }
     }
 
-    fn handle_test_return(&mut self, raw_result : c_int) {
-        self.test_result.raw_return_code = raw_result;
+    fn handle_test_return(test_result : &mut TestResult, raw_result : c_int) {
+        test_result.raw_return_code = raw_result;
         // Assert takes predence..
-        if self.test_result.func_error.is_some() {
-            self.test_result.return_code = Some(TestReturnCode::Fail);
+        if test_result.func_error.is_some() {
+            test_result.return_code = Some(TestReturnCode::Fail);
         } else {
-            self.test_result.return_code = TestReturnCode::try_from(raw_result).ok();
+            test_result.return_code = TestReturnCode::try_from(raw_result).ok();
         }
     }
 }
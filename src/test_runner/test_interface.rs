@@ -28,14 +28,19 @@ impl TryFrom<c_int> for TestReturnCode {
     }
 }
 
-pub type TestableFunction = unsafe extern "C" fn(*mut TestRunnerInterface) -> c_int;
+pub type TestableFunction = unsafe extern "C-unwind" fn(*mut TestRunnerInterface) -> c_int;
 pub type PrePostCaseHandler = extern "C" fn(*mut TestRunnerInterface) -> c_void;
-pub type AssertErrorHandler = extern "C" fn(exp : *const c_char, file : *const c_char, line : c_int);
-pub type LogHandlerNonVar =  extern "C" fn (line : c_int, file: *const c_char, format: *const c_char);
+pub type AssertErrorHandler = extern "C-unwind" fn(exp : *const c_char, file : *const c_char, line : c_int);
+pub type LogHandlerNonVar =  extern "C-unwind" fn (line : c_int, file: *const c_char, format: *const c_char);
 pub type LogHandler =  extern "C" fn (line : c_int, file: *const c_char, format: *const c_char, ...) -> c_void;
 //pub type CaseHandler = extern "C" fn(case_handler: *mut TestRunnerInterface);
 pub type CaseHandler = extern "C" fn(case_handler: PrePostCaseHandler);
 pub type DependsHandler = extern "C" fn(name : *const c_char, dep_list: *const c_char);
+pub type CaseParamsHandler = extern "C" fn(name : *const c_char, param_list: *const c_char);
+pub type GetCaseParamHandler = extern "C" fn() -> *const c_char;
+pub type GetParamHandler = extern "C" fn(key : *const c_char) -> *const c_char;
+pub type GetPathHandler = extern "C" fn() -> *const c_char;
+pub type ExpectDeathHandler = extern "C" fn(name : *const c_char, signal : c_int, exit_status : c_int, stderr_pattern : *const c_char);
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct TestRunnerInterface {
@@ -53,6 +58,14 @@ pub struct TestRunnerInterface {
     pub set_post_case_callback : Option<CaseHandler>,
 
     pub case_depends : Option<DependsHandler>,
+
+    // Extensions - not part of the C/C++ V1 interface, appended so the layout above stays intact
+    pub case_params : Option<CaseParamsHandler>,
+    pub get_case_param : Option<GetCaseParamHandler>,
+    pub get_param : Option<GetParamHandler>,
+    pub get_temp_dir : Option<GetPathHandler>,
+    pub get_data_dir : Option<GetPathHandler>,
+    pub expect_death : Option<ExpectDeathHandler>,
 }
 
 
@@ -75,6 +88,12 @@ impl TestRunnerInterface {
 
             case_depends : None,
 
+            case_params : None,
+            get_case_param : None,
+            get_param : None,
+            get_temp_dir : None,
+            get_data_dir : None,
+            expect_death : None,
         };
         return trun;
     }
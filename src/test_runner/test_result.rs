@@ -1,6 +1,7 @@
 use std::ffi::c_int;
+use std::path::PathBuf;
 use std::time::Duration;
-use crate::test_runner::{TestFuncError, TestReturnCode};
+use crate::test_runner::{BenchStats, CheckIssue, Config, LimitViolation, ResourceUsage, Singleton, TestFuncError, TestReturnCode};
 
 #[derive(Debug, Clone)]
 pub struct TestResult {
@@ -14,6 +15,19 @@ pub struct TestResult {
     pub num_assert : u32,           // Note: Always one, if an error occurs - however, embedded or single-threading can have several
 
     pub symbol : String,            // The actual exported symbol
+    pub module_name : String,       // Module of the test function, '-' for globals
+    pub case_name : String,         // Case name without any parameter set
+    pub undefined_params : Vec<String>, // Parameters requested through 'get_param' which were not defined
+    pub temp_dir : Option<PathBuf>,  // The per-test temporary directory, only set if it was kept
+
+    pub resource_usage : Option<ResourceUsage>, // Measured on the test thread
+    pub limit_violation : Option<LimitViolation>, // Set if a resource limit was exceeded, the test is failed
+    pub check_issues : Vec<CheckIssue>, // Found by the checkers (fd leaks, ...), failure issues fail the test
+    pub bench : Option<BenchStats>, // Per-iteration timing statistics in benchmark mode
+    pub bench_regression : Option<String>, // Set if slower than the baseline, the test is failed
+
+    pub flaky : bool,               // Passed, but only after one or more failed attempts (see --retries)
+    pub attempts : Vec<TestResult>, // Previous failed attempts, the result itself is the last attempt
 }
 
 impl TestResult {
@@ -25,7 +39,18 @@ impl TestResult {
             num_assert : 0,
             num_error : 0,
             symbol : String::default(),
+            module_name : String::default(),
+            case_name : String::default(),
             raw_return_code: 0,
+            undefined_params : Vec::new(),
+            temp_dir : None,
+            resource_usage : None,
+            limit_violation : None,
+            check_issues : Vec::new(),
+            bench : None,
+            bench_regression : None,
+            flaky : false,
+            attempts : Vec::new(),
         }
     }
     pub fn print(&self) {
@@ -36,6 +61,7 @@ impl TestResult {
             None => println!("=== INVALID RETURN CODE ({}) for {}", self.raw_return_code, self.symbol),
             Some(rc) => {
                 match rc {
+                    TestReturnCode::Pass if self.flaky => println!("=== FLAKY:\t{}, {} sec, {}, passed on attempt {}", self.symbol, self.exec_duration.as_secs_f32(), self.raw_return_code, self.num_attempts()),
                     TestReturnCode::Pass => println!("=== PASS:\t{}, {} sec, {}", self.symbol, self.exec_duration.as_secs_f32(), self.raw_return_code),
                     TestReturnCode::Fail => println!("=== FAIL:\t{}, {} sec, {}", self.symbol, self.exec_duration.as_secs_f32(), self.raw_return_code),
                     TestReturnCode::FailModule => println!("=== FAIL:\t{}, {} sec, {}", self.symbol, self.exec_duration.as_secs_f32(), self.raw_return_code),
@@ -43,10 +69,85 @@ impl TestResult {
                 }
             }
         }
+        if let Some(usage) = self.resource_usage.as_ref().filter(|_| Config::instance().verbose > 0) {
+            usage.print();
+        }
+        if let Some(bench) = &self.bench {
+            bench.print();
+        }
+        self.print_backtrace();
+        self.print_limit_violation();
+        self.print_check_issues();
+        self.print_bench_regression();
+        self.print_undefined_params();
+        self.print_temp_dir();
         // Empty line in the console output
         println!("");
     }
 
+    // Outcome as stored in the last run state, flaky is not considered a failure
+    pub fn outcome(&self) -> &'static str {
+        if self.flaky {
+            return "flaky";
+        }
+        if self.did_fail() {
+            return "fail";
+        }
+        "pass"
+    }
+
+    // Total number of executions, including the failed attempts
+    pub fn num_attempts(&self) -> usize {
+        self.attempts.len() + 1
+    }
+
+    pub fn print_flaky(&self) {
+        println!("  [flaky]: {}, {} attempts", self.symbol, self.num_attempts());
+        for (idx, attempt) in self.attempts.iter().enumerate() {
+            match &attempt.func_error {
+                Some(err) => println!("    attempt {}: {}", idx + 1, err.describe()),
+                None => println!("    attempt {}: return code {}", idx + 1, attempt.raw_return_code),
+            }
+        }
+    }
+
+    fn print_backtrace(&self) {
+        if let Some(func_error) = self.func_error.as_ref().filter(|func_error| !func_error.backtrace.is_empty()) {
+            println!("  Backtrace:");
+            func_error.print_backtrace();
+        }
+    }
+
+    fn print_check_issues(&self) {
+        for issue in &self.check_issues {
+            println!("  {}", issue);
+        }
+    }
+
+    fn print_limit_violation(&self) {
+        if let Some(violation) = &self.limit_violation {
+            println!("  Resource limit exceeded: {}", violation);
+        }
+    }
+
+    fn print_bench_regression(&self) {
+        if let Some(regression) = &self.bench_regression {
+            println!("  Slower than baseline: {}", regression);
+        }
+    }
+
+    fn print_temp_dir(&self) {
+        if let Some(temp_dir) = &self.temp_dir {
+            println!("  Temp dir: {}", temp_dir.display());
+        }
+    }
+
+    fn print_undefined_params(&self) {
+        if !self.undefined_params.is_empty() {
+            println!("  Undefined parameter(s): {}", self.undefined_params.join(", "));
+        }
+    }
+
     pub fn did_pass(&self) -> bool {
         match &self.return_code {
             Some(rc) => {
@@ -80,7 +181,12 @@ impl TestResult {
     pub fn print_failure(&self) {
         if self.func_error.is_some() {
             let ass_err = &self.func_error.as_ref().unwrap();
-            println!("  [Tma]: {}, {}:{}, {}", self.symbol, ass_err.file, ass_err.line, ass_err.message);
+            println!("  [Tma]: {}, {}", self.symbol, ass_err.describe());
+            self.print_backtrace();
+            self.print_limit_violation();
+            self.print_check_issues();
+            self.print_undefined_params();
+            self.print_temp_dir();
             return;
         }
         if self.return_code.is_some() {
@@ -99,6 +205,11 @@ impl TestResult {
         } else {
             println!("  [tma]: {}", self.symbol);
         }
+        self.print_limit_violation();
+        self.print_check_issues();
+        self.print_bench_regression();
+        self.print_undefined_params();
+        self.print_temp_dir();
     }
 
 }
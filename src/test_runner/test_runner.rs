@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::collections::{HashMap};
-use crate::test_runner::{Config, Singleton, DynLibrary, Module, TestFunction, TestFunctionRef, TestScope, TestType, ResultSummary, DynLibraryRef};
+use std::collections::{HashMap, HashSet};
+use crate::test_runner::{Config, Singleton, DynLibrary, Module, TestFunction, TestFunctionRef, TestScope, TestType, ResultSummary, DynLibraryRef, CaseStats, LastRun, LastRunEntry, CaseRecord, BenchBaseline, DurationEstimates, canonical_library_name, library_file_name, stop_requested, stop_before_start};
 
 //
 // The runner holds all test details for a single library..
@@ -10,6 +10,8 @@ pub struct TestRunner {
     //library : DynLibrary,
     library : DynLibraryRef,
     modules : HashMap<String, Module>,
+    // Module names in execution order, see 'schedule_modules' - empty executes in map order
+    schedule : Vec<String>,
     global_main : Option<TestFunctionRef>,
     global_exit : Option<TestFunctionRef>,
     global_results : ResultSummary,
@@ -23,6 +25,7 @@ impl TestRunner {
         let mut inst = TestRunner {
             library : Rc::new(RefCell::new(DynLibrary::new(filename))),
             modules : HashMap::new(),
+            schedule : Vec::new(),
             global_main : None,
             global_exit : None,
             global_results : ResultSummary::new("-"),       // special 'global' name
@@ -184,6 +187,13 @@ impl TestRunner {
     // Execution
     //
     pub fn execute_tests(&mut self) {
+        if stop_requested().is_some() {
+            // A library without selected modules is not skipped because of the stop
+            if self.modules.values().any(|module| module.should_execute()) {
+                stop_before_start();
+            }
+            return;
+        }
 
         println!("---> Start Library  \t{}", self.library.borrow().name);
 
@@ -228,10 +238,18 @@ impl TestRunner {
     // Execute tests in all modules
     //
     fn execute_all_modules(&mut self) {
-        for (_, module) in self.modules.iter_mut() {
+        let names : Vec<String> = match self.schedule.is_empty() {
+            true => self.modules.keys().cloned().collect(),
+            false => self.schedule.clone(),
+        };
+        for name in names {
+            let module = self.modules.get_mut(&name).expect("scheduled module");
             if !module.should_execute() {
                 continue;
             }
+            if stop_before_start().is_some() {
+                break;
+            }
 
             module.execute(&self.library);
 
@@ -243,17 +261,160 @@ impl TestRunner {
         };
     }
 
-    pub fn print_results(&self) {
+    //
+    // Restrict execution to the cases that failed in the last run
+    // Dependencies don't need any special handling, they are executed regardless of selection.
+    //
+    pub fn select_failed(&mut self, last_run : &LastRun) {
+        let library = self.library.borrow().name.clone();
+
+        for module in self.modules.values_mut() {
+            module.selected = last_run.is_failed_module(&library, &module.name);
+            for tc in &module.test_cases {
+                let mut tc = tc.borrow_mut();
+                tc.selected = last_run.is_failed_case(&library, &module.name, &tc.case_name);
+            }
+        }
+
+        // Globals are setup/teardown for the modules, needed if anything in this library is executed
+        let library_selected = last_run.is_failed_library(&library);
+        for func in self.global_main.iter().chain(self.global_exit.iter()) {
+            func.borrow_mut().selected = library_selected;
+        }
+    }
+
+    // Order the modules longest first, the order is kept without any history
+    pub fn schedule_modules(&mut self, estimates : &DurationEstimates) {
+        if estimates.is_empty() {
+            return;
+        }
+        let library = library_file_name(&self.library.borrow().name);
+        let mut names : Vec<String> = self.modules.keys().cloned().collect();
+        names.sort();
+        let mut durations : Vec<(f64, String)> = names.into_iter().map(|name| (estimates.module(&library, &name), name)).collect();
+        estimates.longest_first(&mut durations, |(duration_sec, _)| *duration_sec);
+        self.schedule = durations.into_iter().map(|(_, name)| name).collect();
+    }
+
+    // Expected duration of the selected modules in seconds
+    pub fn expected_duration(&self, estimates : &DurationEstimates) -> f64 {
+        let library = library_file_name(&self.library.borrow().name);
+        self.modules
+            .values()
+            .filter(|module| module.should_execute())
+            .map(|module| estimates.module(&library, &module.name))
+            .sum()
+    }
+
+    // Returns the library file and module names of all modules selected for execution
+    pub fn selected_modules(&self) -> Vec<(String, String)> {
+        let library = library_file_name(&self.library.borrow().name);
+        self.modules
+            .values()
+            .filter(|module| module.should_execute())
+            .map(|module| (library.clone(), module.name.clone()))
+            .collect()
+    }
+
+    //
+    // Restrict execution to the modules of the current shard
+    // Like with --rerun-failed the globals are only needed if anything in this library is executed.
+    //
+    pub fn select_modules(&mut self, in_shard : &dyn Fn(&str, &str) -> bool) {
+        let library = library_file_name(&self.library.borrow().name);
+
+        let mut library_selected = false;
+        for module in self.modules.values_mut() {
+            module.selected = module.selected && in_shard(&library, &module.name);
+            library_selected |= module.selected;
+        }
+        for func in self.global_main.iter().chain(self.global_exit.iter()) {
+            let mut func = func.borrow_mut();
+            func.selected = func.selected && library_selected;
+        }
+    }
+
+    // Returns the state of all executed cases, to be stored as the last run
+    pub fn last_run_entries(&self) -> Vec<LastRunEntry> {
+        let library = self.library.borrow().name.clone();
+        self.test_results
+            .iter()
+            .flat_map(|r| r.test_results.iter())
+            .map(|tr| LastRunEntry::new(&library, &tr.module_name, &tr.case_name, &tr.symbol, tr.outcome()))
+            .collect()
+    }
+
+    // Returns all executed cases as history records
+    pub fn history_records(&self) -> Vec<CaseRecord> {
+        let library = self.library.borrow().name.clone();
+        self.test_results
+            .iter()
+            .flat_map(|r| r.test_results.iter())
+            .map(|tr| {
+                let mut record = CaseRecord::new(&library, &tr.module_name, &tr.case_name, &tr.symbol, tr.outcome(), tr.exec_duration.as_secs_f64());
+                record.resource_usage = tr.resource_usage.clone();
+                record
+            })
+            .collect()
+    }
+
+    // Returns the library (as recorded in the history) and the test functions it exports
+    pub fn exported_symbols(&self) -> (String, HashSet<String>) {
+        let library = self.library.borrow();
+        (canonical_library_name(&library.name), library.exports.iter().cloned().collect())
+    }
+
+    // Add the benchmark results of all executed cases to a baseline
+    pub fn add_bench_results(&self, baseline : &mut BenchBaseline) {
+        let library = self.library.borrow().name.clone();
+        for tr in self.test_results.iter().flat_map(|r| r.test_results.iter()) {
+            if let Some(bench) = &tr.bench {
+                baseline.add(&library, &tr.symbol, bench);
+            }
+        }
+    }
+
+    //
+    // Reset all functions and the global results so the tests can be executed again, gathered results are kept
+    //
+    pub fn reset(&mut self) {
+        for func in self.global_main.iter().chain(self.global_exit.iter()) {
+            func.borrow_mut().reset();
+        }
+        for module in self.modules.values_mut() {
+            module.reset();
+        }
+        self.global_results = ResultSummary::new("-");
+    }
+
+    // Returns number of executed and failed tests over all executions
+    pub fn count_results(&self) -> (i32, i32) {
         let mut num_failed = 0;
         let mut num_executed = 0;
 
-
         for r in &self.test_results {
             // We only gather number of executed
             num_executed += r.tests_executed;
             num_failed += r.tests_failed;
         } // for
 
+        (num_executed, num_failed)
+    }
+
+    // Returns the cases scheduled for execution but never started, i.e. skipped when the run was stopped
+    pub fn not_executed_cases(&self) -> Vec<String> {
+        self.modules
+            .values()
+            .filter(|module| module.should_execute())
+            .flat_map(|module| module.test_cases.iter())
+            .filter(|tc| tc.borrow().should_execute())
+            .map(|tc| tc.borrow().display_symbol())
+            .collect()
+    }
+
+    pub fn print_results(&self) {
+        let (num_executed, num_failed) = self.count_results();
+
         println!("Tests Executed: {}", num_executed);
         println!("Tests Failed..: {}", num_failed);
 
@@ -264,5 +425,25 @@ impl TestRunner {
             }
         }
 
+        let cfg = Config::instance();
+        if cfg.retries > 0 {
+            let num_flaky : i32 = self.test_results.iter().map(|r| r.tests_flaky).sum();
+            println!("Tests Flaky...: {}", num_flaky);
+            if num_flaky > 0 {
+                println!("Flaky:");
+                for r in &self.test_results {
+                    r.print_flaky();
+                }
+            }
+        }
+
+        if cfg.repeat.is_some() || cfg.until_fail {
+            println!("Per case:");
+            let results = self.test_results.iter().flat_map(|r| r.test_results.iter());
+            for stats in CaseStats::from_results(results) {
+                stats.print();
+            }
+        }
+
     }
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+use std::{fs, io, thread};
+use libc::pid_t;
+use crate::test_runner::{is_runner_thread, CheckIssue};
+
+// Threads which are about to terminate may still be listed right after the test returns
+const EXIT_GRACE_PERIOD : Duration = Duration::from_millis(20);
+
+//
+// Threads of the process, as listed in /proc/self/task, see --check-thread-leaks
+//
+pub struct ThreadSnapshot {
+    tids : BTreeSet<pid_t>,
+}
+
+impl ThreadSnapshot {
+    pub fn take() -> io::Result<ThreadSnapshot> {
+        let tids = fs::read_dir("/proc/self/task")?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok()))
+            .collect();
+        Ok(ThreadSnapshot { tids })
+    }
+
+    // Threads running now which were not in 'before', threads started by the runner it-self are excluded
+    pub fn started_since(&self, before : &ThreadSnapshot) -> Vec<pid_t> {
+        self.tids
+            .iter()
+            .filter(|tid| !before.tids.contains(tid) && !is_runner_thread(**tid))
+            .copied()
+            .collect()
+    }
+
+    // Compare with a snapshot taken before the test, one issue per lingering thread
+    pub fn check_leaks(before : &ThreadSnapshot, is_failure : bool) -> Vec<CheckIssue> {
+        let mut lingering = match Self::lingering_threads(before) {
+            Ok(tids) => tids,
+            Err(err) => {
+                println!("ERR: Unable to read process threads: {}", err);
+                return Vec::new();
+            }
+        };
+        if !lingering.is_empty() {
+            thread::sleep(EXIT_GRACE_PERIOD);
+            lingering = Self::lingering_threads(before).unwrap_or(lingering);
+        }
+        lingering
+            .iter()
+            .map(|tid| CheckIssue::new("thread-leak", &format!("thread {} ({}) still running", tid, thread_name(*tid)), is_failure))
+            .collect()
+    }
+
+    fn lingering_threads(before : &ThreadSnapshot) -> io::Result<Vec<pid_t>> {
+        Ok(ThreadSnapshot::take()?.started_since(before))
+    }
+}
+
+fn thread_name(tid : pid_t) -> String {
+    match fs::read_to_string(format!("/proc/self/task/{}/comm", tid)) {
+        Ok(name) => name.trim_end().to_string(),
+        Err(_) => "<exited>".to_string(),
+    }
+}